@@ -0,0 +1,91 @@
+use std::collections::HashMap;
+
+use super::aseprite::AsepriteSheet;
+use crate::{
+    components::{Animation, AnimationSheet, LoopMode},
+    loader,
+};
+
+/**
+ * Animations built from the Aseprite exports in resources/animations, one set per file and
+ * one animation per frame tag. The frames are looked up by filename in the spritesheets,
+ * so the export's frames need to be packed into one of them, or loaded as one.
+ */
+#[derive(Default)]
+pub struct AnimationLibrary {
+    sets: HashMap<String, HashMap<String, Animation>>,
+}
+
+impl AnimationLibrary {
+    pub fn new() -> Self {
+        let mut library = AnimationLibrary {
+            sets: HashMap::new(),
+        };
+        for name in loader::list_resources("resources/animations", "json") {
+            if let Err(err) = library.load(&name) {
+                eprintln!("{}, leaving out the animations of {}", err, name);
+            }
+        }
+        library
+    }
+
+    fn load(&mut self, name: &str) -> Result<(), String> {
+        let path = format!("resources/animations/{}.json", name);
+        let text = loader::read_text_from_file(&path)
+            .map_err(|_| format!("Could not read animations at {}", path))?;
+        let sheet: AsepriteSheet = serde_json::from_str(&text)
+            .map_err(|err| format!("Could not parse animations at {}: {}", path, err))?;
+
+        let mut animations = HashMap::new();
+        for tag in &sheet.meta.frame_tags {
+            let mut frames: Vec<_> = sheet
+                .tag_frames(tag)
+                .map_err(|err| format!("{} in {}", err, path))?
+                .iter()
+                .collect();
+            let loop_mode = match tag.direction.as_ref() {
+                "forward" => LoopMode::Loop,
+                "reverse" => {
+                    frames.reverse();
+                    LoopMode::Loop
+                }
+                "pingpong" => LoopMode::PingPong,
+                direction => {
+                    return Err(format!(
+                        "Unknown direction {} for tag {} in {}",
+                        direction, tag.name, path
+                    ))
+                }
+            };
+
+            let mut animation =
+                Animation::new(frames.iter().map(|frame| frame.filename.clone()).collect())
+                    .loop_mode(loop_mode);
+            for (i, frame) in frames.iter().enumerate() {
+                animation = animation.frame_duration(i, frame.duration as f32 / 1000.0);
+            }
+
+            animations.insert(tag.name.clone(), animation);
+        }
+
+        self.sets.insert(name.to_string(), animations);
+        Ok(())
+    }
+
+    /// Builds an AnimationSheet with every animation of the set, by the name of its file
+    pub fn build_sheet(&self, name: &str) -> AnimationSheet {
+        let animations = self
+            .sets
+            .get(name)
+            .unwrap_or_else(|| panic!("No animations loaded with name {}", name));
+
+        let mut sheet = AnimationSheet::new(0.1);
+        // sorted so the first animation, which the sheet starts on, is always the same
+        let mut names: Vec<&String> = animations.keys().collect();
+        names.sort();
+        for name in names {
+            sheet.add(name.clone(), animations.get(name).unwrap().clone());
+        }
+        sheet
+    }
+}
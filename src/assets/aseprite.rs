@@ -0,0 +1,86 @@
+use serde_derive::{Deserialize, Serialize};
+
+/**
+ * The parts of Aseprite's JSON export used for animations. Export with the array format
+ * (--format json-array) and --list-tags. Frame rects are read through Spritesheet instead.
+ */
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AsepriteFrame {
+    pub filename: String,
+    /// In milliseconds
+    pub duration: u32,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct FrameTag {
+    pub name: String,
+    pub from: usize,
+    pub to: usize,
+    /// forward, reverse or pingpong
+    pub direction: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AsepriteMeta {
+    #[serde(default)]
+    pub frame_tags: Vec<FrameTag>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AsepriteSheet {
+    pub frames: Vec<AsepriteFrame>,
+    pub meta: AsepriteMeta,
+}
+
+impl AsepriteSheet {
+    /// The frames a tag covers, in order, or an error if the tag runs past the export's frames
+    pub fn tag_frames(&self, tag: &FrameTag) -> Result<&[AsepriteFrame], String> {
+        if tag.from > tag.to || tag.to >= self.frames.len() {
+            return Err(format!(
+                "Tag {} covers frames {} to {}, but there are {} frames",
+                tag.name,
+                tag.from,
+                tag.to,
+                self.frames.len()
+            ));
+        }
+        Ok(&self.frames[tag.from..=tag.to])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sheet_with_tag(from: usize, to: usize) -> AsepriteSheet {
+        let json = format!(
+            r#"{{
+                "frames": [
+                    {{ "filename": "walk 0", "duration": 100 }},
+                    {{ "filename": "walk 1", "duration": 100 }}
+                ],
+                "meta": {{
+                    "frameTags": [{{ "name": "walk", "from": {}, "to": {}, "direction": "forward" }}]
+                }}
+            }}"#,
+            from, to
+        );
+        serde_json::from_str(&json).unwrap()
+    }
+
+    #[test]
+    fn tag_frames_cover_the_tag() {
+        let sheet = sheet_with_tag(0, 1);
+        let frames = sheet.tag_frames(&sheet.meta.frame_tags[0]).unwrap();
+        assert_eq!(frames.len(), 2);
+        assert_eq!(frames[1].filename, "walk 1");
+    }
+
+    #[test]
+    fn tags_past_the_frames_are_errors_naming_the_tag() {
+        let sheet = sheet_with_tag(1, 2);
+        let err = sheet.tag_frames(&sheet.meta.frame_tags[0]).unwrap_err();
+        assert!(err.contains("walk"));
+    }
+}
@@ -0,0 +1,39 @@
+use std::collections::HashMap;
+
+use gfx_glyph::FontId;
+
+/**
+ * Names of the fonts the glyph brush was built with. Ids follow the order of the font names,
+ * so the first one is the default font.
+ */
+pub struct FontMap {
+    fonts: HashMap<String, FontId>,
+}
+
+impl FontMap {
+    pub fn new(font_names: &[&str]) -> Self {
+        let fonts = font_names
+            .iter()
+            .enumerate()
+            .map(|(i, name)| (name.to_string(), FontId(i)))
+            .collect();
+
+        FontMap { fonts }
+    }
+
+    pub fn get(&self, name: &str) -> FontId {
+        if let Some(font_id) = self.fonts.get(name) {
+            *font_id
+        } else {
+            panic!("Could not find font by name {}", name);
+        }
+    }
+}
+
+impl Default for FontMap {
+    fn default() -> Self {
+        FontMap {
+            fonts: HashMap::new(),
+        }
+    }
+}
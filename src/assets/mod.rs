@@ -1,2 +1,6 @@
+pub mod animation_library;
+pub mod aseprite;
+pub mod font_map;
+pub mod prefabs;
 pub mod spritesheet;
 pub mod spritesheet_map;
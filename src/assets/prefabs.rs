@@ -0,0 +1,84 @@
+use std::collections::HashMap;
+
+use serde_json::Value;
+use tiled::{Properties, PropertyValue};
+
+use crate::loader;
+
+/**
+ * Default properties for each Tiled object type, from resources/prefabs.json. The object's
+ * own properties win over the prefab's, and are otherwise read the same way.
+ * Strings like #aarrggbb become colours, as Tiled writes them.
+ */
+#[derive(Default)]
+pub struct PrefabRegistry {
+    prefabs: HashMap<String, Properties>,
+}
+
+fn property_from_json(name: &str, value: &Value) -> PropertyValue {
+    match value {
+        Value::Bool(value) => PropertyValue::BoolValue(*value),
+        Value::Number(number) => {
+            if let Some(value) = number.as_i64() {
+                PropertyValue::IntValue(value as i32)
+            } else {
+                PropertyValue::FloatValue(number.as_f64().unwrap() as f32)
+            }
+        }
+        Value::String(value) => {
+            let hex = value.trim_start_matches('#');
+            if value.starts_with('#') && hex.len() == 8 {
+                let argb = u32::from_str_radix(hex, 16)
+                    .unwrap_or_else(|_| panic!("Bad colour {} for {}", value, name));
+                PropertyValue::ColorValue(argb)
+            } else {
+                PropertyValue::StringValue(value.clone())
+            }
+        }
+        _ => panic!(
+            "Prefab property {} should be a bool, number or string",
+            name
+        ),
+    }
+}
+
+impl PrefabRegistry {
+    pub fn new() -> Self {
+        let text = loader::read_text_from_file("resources/prefabs.json")
+            .unwrap_or_else(|_| panic!("Could not read resources/prefabs.json"));
+        PrefabRegistry::from_json(&text)
+    }
+
+    /// Prefabs from JSON laid out like resources/prefabs.json
+    pub fn from_json(text: &str) -> Self {
+        let prefabs: HashMap<String, HashMap<String, Value>> = serde_json::from_str(text)
+            .unwrap_or_else(|err| panic!("Could not parse resources/prefabs.json: {}", err));
+
+        PrefabRegistry {
+            prefabs: prefabs
+                .into_iter()
+                .map(|(object_type, properties)| {
+                    let properties = properties
+                        .iter()
+                        .map(|(name, value)| (name.clone(), property_from_json(name, value)))
+                        .collect();
+                    (object_type, properties)
+                })
+                .collect(),
+        }
+    }
+
+    /// The object's properties on top of the defaults for its type property, if it has one
+    pub fn get_properties(&self, properties: &Properties) -> Properties {
+        let prefab = match properties.get("type") {
+            Some(PropertyValue::StringValue(object_type)) => self.prefabs.get(object_type),
+            _ => None,
+        };
+
+        let mut merged = prefab.cloned().unwrap_or_else(HashMap::new);
+        for (name, value) in properties {
+            merged.insert(name.clone(), value.clone());
+        }
+        merged
+    }
+}
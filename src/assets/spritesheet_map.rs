@@ -39,4 +39,33 @@ where
             sheet_name_map,
         }
     }
+
+    /**
+     * Finds the sheet a frame belongs to, its texture coordinates as [x, y, x2, y2] and its
+     * size. None when no sheet has the frame
+     */
+    pub fn get_frame_region(&self, frame_name: &str) -> Option<(&str, [f32; 4], (f32, f32))> {
+        let sheet_name = self.frame_to_sheet_name.get(frame_name)?;
+        let (spritesheet, _) = self.sheet_name_map.get(sheet_name)?;
+        let region = spritesheet
+            .frames
+            .iter()
+            .find(|frame| frame.filename == frame_name)?;
+        let sw = spritesheet.meta.size.w as f32;
+        let sh = spritesheet.meta.size.h as f32;
+
+        Some((
+            sheet_name,
+            [
+                region.frame.x as f32 / sw,
+                region.frame.y as f32 / sh,
+                (region.frame.x as f32 + region.frame.w as f32) / sw,
+                (region.frame.y as f32 + region.frame.h as f32) / sh,
+            ],
+            (
+                region.sprite_source_size.w as f32,
+                region.sprite_source_size.h as f32,
+            ),
+        ))
+    }
 }
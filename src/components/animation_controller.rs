@@ -0,0 +1,402 @@
+use std::collections::HashMap;
+
+use serde_derive::Deserialize;
+use specs::{Component, VecStorage};
+
+use crate::loader;
+
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[serde(untagged)]
+pub enum ParamValue {
+    Bool(bool),
+    Float(f32),
+    Text(String),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Comparison {
+    Eq,
+    Ne,
+    Gt,
+    Lt,
+    Ge,
+    Le,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct Condition {
+    pub param: String,
+    pub op: Comparison,
+    pub value: ParamValue,
+}
+
+impl Condition {
+    /// Ordering only applies to floats, bools and text can only be compared for equality
+    fn is_met(&self, value: Option<&ParamValue>) -> bool {
+        let value = match value {
+            Some(value) => value,
+            None => return false,
+        };
+
+        match (self.op, value, &self.value) {
+            (Comparison::Eq, value, expected) => value == expected,
+            (Comparison::Ne, value, expected) => value != expected,
+            (Comparison::Gt, ParamValue::Float(value), ParamValue::Float(expected)) => {
+                value > expected
+            }
+            (Comparison::Lt, ParamValue::Float(value), ParamValue::Float(expected)) => {
+                value < expected
+            }
+            (Comparison::Ge, ParamValue::Float(value), ParamValue::Float(expected)) => {
+                value >= expected
+            }
+            (Comparison::Le, ParamValue::Float(value), ParamValue::Float(expected)) => {
+                value <= expected
+            }
+            _ => false,
+        }
+    }
+}
+
+fn default_true() -> bool {
+    true
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct ControllerState {
+    pub animation: String,
+    /// Picks animation_<direction> from the entity's Facing, see Facing::get_animation
+    #[serde(default)]
+    pub directional: bool,
+    /// Mirrors the sprite, so one animation can serve both left and right. Directional
+    /// states work out flipping from the Facing instead
+    #[serde(default)]
+    pub flip: bool,
+    /// Stopped states hold whatever frame the animation was on, unless frame is set
+    #[serde(default = "default_true")]
+    pub playing: bool,
+    /// Frame to go to when entering the state, like a rest pose when stopping
+    #[serde(default)]
+    pub frame: Option<usize>,
+    /// Shown as a still on frame when the state's own animation is missing, so idle can
+    /// use the walk frames until idle art exists for a facing
+    #[serde(default)]
+    pub fallback: Option<String>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct Transition {
+    /// A state name, or * for any state
+    pub from: String,
+    pub to: String,
+    #[serde(default)]
+    pub conditions: Vec<Condition>,
+    /// Only taken once the current animation has finished, for Once and Hold animations
+    #[serde(default)]
+    pub when_finished: bool,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct ControllerDefinition {
+    pub initial: String,
+    #[serde(default)]
+    pub parameters: HashMap<String, ParamValue>,
+    pub states: HashMap<String, ControllerState>,
+    /// Checked in order, the first one that applies is taken
+    #[serde(default)]
+    pub transitions: Vec<Transition>,
+}
+
+/**
+ * State machine that picks the AnimationSheet's animation from parameters, see
+ * AnimationControllerSystem. Definitions live in resources/controllers, so gameplay code
+ * only has to set parameters. The state_time parameter is kept up to date with the seconds
+ * spent in the current state, for things like fidgeting after standing still for a while.
+ */
+pub struct AnimationController {
+    pub definition: ControllerDefinition,
+    pub state: String,
+    pub parameters: HashMap<String, ParamValue>,
+    pub state_time: f32,
+    started: bool,
+}
+
+impl AnimationController {
+    pub fn new(definition: ControllerDefinition) -> Self {
+        if !definition.states.contains_key(&definition.initial) {
+            panic!("No state for initial state {}", definition.initial);
+        }
+
+        AnimationController {
+            state: definition.initial.clone(),
+            parameters: definition.parameters.clone(),
+            definition,
+            state_time: 0.0,
+            started: false,
+        }
+    }
+
+    /// Loads resources/controllers/{name}.json
+    pub fn from_file(name: &str) -> Self {
+        let path = format!("resources/controllers/{}.json", name);
+        let text = loader::read_text_from_file(&path)
+            .unwrap_or_else(|_| panic!("Could not read controller at {}", path));
+        let definition = serde_json::from_str(&text)
+            .unwrap_or_else(|err| panic!("Could not parse controller at {}: {}", path, err));
+        AnimationController::new(definition)
+    }
+
+    pub fn set_float(&mut self, name: &str, value: f32) {
+        self.parameters
+            .insert(name.to_string(), ParamValue::Float(value));
+    }
+
+    pub fn set_bool(&mut self, name: &str, value: bool) {
+        self.parameters
+            .insert(name.to_string(), ParamValue::Bool(value));
+    }
+
+    pub fn set_text(&mut self, name: &str, value: &str) {
+        self.parameters
+            .insert(name.to_string(), ParamValue::Text(value.to_string()));
+    }
+
+    pub fn get(&self, name: &str) -> Option<&ParamValue> {
+        self.parameters.get(name)
+    }
+
+    pub fn get_state(&self) -> &ControllerState {
+        self.definition.states.get(&self.state).unwrap()
+    }
+
+    /**
+     * Moves to the first state a transition allows, if any. Returns true if a state was
+     * entered, which includes the initial state on the first update
+     */
+    pub fn update(&mut self, dt: f32, animation_finished: bool) -> bool {
+        self.state_time += dt;
+        let state_time = self.state_time;
+        self.set_float("state_time", state_time);
+
+        let next_state = self
+            .definition
+            .transitions
+            .iter()
+            .filter(|transition| transition.from == "*" || transition.from == self.state)
+            .filter(|transition| transition.to != self.state)
+            .filter(|transition| !transition.when_finished || animation_finished)
+            .find(|transition| {
+                transition
+                    .conditions
+                    .iter()
+                    .all(|condition| condition.is_met(self.parameters.get(&condition.param)))
+            })
+            .map(|transition| transition.to.clone());
+
+        if let Some(next_state) = next_state {
+            if !self.definition.states.contains_key(&next_state) {
+                panic!("No state for transition to {}", next_state);
+            }
+            self.state = next_state;
+            self.state_time = 0.0;
+            self.started = true;
+            true
+        } else if !self.started {
+            self.started = true;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+impl Component for AnimationController {
+    type Storage = VecStorage<Self>;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn condition(op: Comparison, value: ParamValue) -> Condition {
+        Condition {
+            param: "value".to_string(),
+            op,
+            value,
+        }
+    }
+
+    fn controller() -> AnimationController {
+        let definition = serde_json::from_str(
+            r#"{
+                "initial": "idle",
+                "parameters": { "speed": 0.0, "grounded": true },
+                "states": {
+                    "idle": { "animation": "idle" },
+                    "walk": { "animation": "walk", "directional": true },
+                    "fidget": { "animation": "fidget" },
+                    "jump": { "animation": "jump" },
+                    "land": { "animation": "land" }
+                },
+                "transitions": [
+                    { "from": "*", "to": "jump", "conditions": [
+                        { "param": "grounded", "op": "eq", "value": false }
+                    ] },
+                    { "from": "idle", "to": "walk", "conditions": [
+                        { "param": "speed", "op": "gt", "value": 0.0 }
+                    ] },
+                    { "from": "walk", "to": "idle", "conditions": [
+                        { "param": "speed", "op": "le", "value": 0.0 }
+                    ] },
+                    { "from": "idle", "to": "fidget", "conditions": [
+                        { "param": "state_time", "op": "ge", "value": 3.0 }
+                    ] },
+                    { "from": "fidget", "to": "idle", "when_finished": true },
+                    { "from": "jump", "to": "land", "conditions": [
+                        { "param": "grounded", "op": "eq", "value": true }
+                    ] },
+                    { "from": "land", "to": "idle", "when_finished": true }
+                ]
+            }"#,
+        )
+        .unwrap();
+        AnimationController::new(definition)
+    }
+
+    #[test]
+    fn equality_works_for_every_type() {
+        let eq_bool = condition(Comparison::Eq, ParamValue::Bool(true));
+        assert!(eq_bool.is_met(Some(&ParamValue::Bool(true))));
+        assert!(!eq_bool.is_met(Some(&ParamValue::Bool(false))));
+
+        let eq_text = condition(Comparison::Eq, ParamValue::Text("sword".to_string()));
+        assert!(eq_text.is_met(Some(&ParamValue::Text("sword".to_string()))));
+        assert!(!eq_text.is_met(Some(&ParamValue::Text("bow".to_string()))));
+
+        let ne_float = condition(Comparison::Ne, ParamValue::Float(1.0));
+        assert!(ne_float.is_met(Some(&ParamValue::Float(2.0))));
+        assert!(!ne_float.is_met(Some(&ParamValue::Float(1.0))));
+    }
+
+    #[test]
+    fn ordering_compares_floats() {
+        let at = |op, value| condition(op, ParamValue::Float(1.0)).is_met(Some(&value));
+        let below = ParamValue::Float(0.5);
+        let equal = ParamValue::Float(1.0);
+        let above = ParamValue::Float(1.5);
+
+        assert!(at(Comparison::Gt, above.clone()));
+        assert!(!at(Comparison::Gt, equal.clone()));
+        assert!(at(Comparison::Ge, equal.clone()));
+        assert!(!at(Comparison::Ge, below.clone()));
+        assert!(at(Comparison::Lt, below.clone()));
+        assert!(!at(Comparison::Lt, equal.clone()));
+        assert!(at(Comparison::Le, equal));
+        assert!(!at(Comparison::Le, above));
+    }
+
+    #[test]
+    fn ordering_other_types_is_never_met() {
+        let gt_text = condition(Comparison::Gt, ParamValue::Text("a".to_string()));
+        assert!(!gt_text.is_met(Some(&ParamValue::Text("b".to_string()))));
+
+        let lt_bool = condition(Comparison::Lt, ParamValue::Bool(true));
+        assert!(!lt_bool.is_met(Some(&ParamValue::Bool(false))));
+    }
+
+    #[test]
+    fn mismatched_or_missing_values_are_not_met() {
+        let eq_float = condition(Comparison::Eq, ParamValue::Float(1.0));
+        assert!(!eq_float.is_met(Some(&ParamValue::Bool(true))));
+        assert!(!eq_float.is_met(None));
+
+        let ne_float = condition(Comparison::Ne, ParamValue::Float(1.0));
+        assert!(!ne_float.is_met(None));
+    }
+
+    #[test]
+    fn first_update_enters_the_initial_state() {
+        let mut controller = controller();
+
+        assert!(controller.update(0.1, false));
+        assert_eq!(controller.state, "idle");
+        assert!(!controller.update(0.1, false));
+    }
+
+    #[test]
+    fn parameters_drive_transitions() {
+        let mut controller = controller();
+        controller.update(0.1, false);
+
+        controller.set_float("speed", 1.0);
+        assert!(controller.update(0.1, false));
+        assert_eq!(controller.state, "walk");
+        assert_eq!(controller.state_time, 0.0);
+        assert!(!controller.update(0.1, false));
+
+        controller.set_float("speed", 0.0);
+        assert!(controller.update(0.1, false));
+        assert_eq!(controller.state, "idle");
+    }
+
+    #[test]
+    fn any_state_transitions_apply_first_and_only_once() {
+        let mut controller = controller();
+        controller.update(0.1, false);
+        controller.set_float("speed", 1.0);
+        controller.set_bool("grounded", false);
+
+        assert!(controller.update(0.1, false));
+        assert_eq!(controller.state, "jump");
+        assert!(!controller.update(0.1, false));
+        assert_eq!(controller.state, "jump");
+
+        controller.set_bool("grounded", true);
+        assert!(controller.update(0.1, false));
+        assert_eq!(controller.state, "land");
+    }
+
+    #[test]
+    fn when_finished_waits_for_the_animation() {
+        let mut controller = controller();
+        controller.update(0.1, false);
+        controller.set_bool("grounded", false);
+        controller.update(0.1, false);
+        controller.set_bool("grounded", true);
+        controller.update(0.1, false);
+        assert_eq!(controller.state, "land");
+
+        assert!(!controller.update(0.1, false));
+        assert_eq!(controller.state, "land");
+        assert!(controller.update(0.1, true));
+        assert_eq!(controller.state, "idle");
+    }
+
+    #[test]
+    fn state_time_counts_from_entering_the_state() {
+        let mut controller = controller();
+        controller.update(1.0, false);
+        controller.update(1.5, false);
+        assert_eq!(controller.get("state_time"), Some(&ParamValue::Float(2.5)));
+        assert_eq!(controller.state, "idle");
+
+        assert!(controller.update(0.5, false));
+        assert_eq!(controller.state, "fidget");
+        assert!(controller.update(0.1, true));
+        assert_eq!(controller.state, "idle");
+
+        // back to zero on entering idle again, so no fidget straight away
+        assert!(!controller.update(2.0, false));
+        assert_eq!(controller.state, "idle");
+        assert!(controller.update(1.0, false));
+        assert_eq!(controller.state, "fidget");
+    }
+
+    #[test]
+    #[should_panic]
+    fn missing_initial_state_panics() {
+        let definition = serde_json::from_str(r#"{ "initial": "idle", "states": {} }"#).unwrap();
+        AnimationController::new(definition);
+    }
+}
@@ -1,13 +1,109 @@
-use specs::{Component, VecStorage};
+use specs::{Component, Entity, VecStorage};
 use std::collections::HashMap;
 
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum LoopMode {
+    Loop,
+    /// Plays through once, then goes back to the first frame and stops
+    Once,
+    /// Plays forwards then backwards
+    PingPong,
+    /// Plays through once and stays on the last frame
+    Hold,
+}
+
+impl LoopMode {
+    /// Parses the names used in map properties: loop, once, ping_pong and hold
+    pub fn from_name(name: &str) -> Option<LoopMode> {
+        match name {
+            "loop" => Some(LoopMode::Loop),
+            "once" => Some(LoopMode::Once),
+            "ping_pong" => Some(LoopMode::PingPong),
+            "hold" => Some(LoopMode::Hold),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct Animation {
+    pub frames: Vec<String>,
+    /// Seconds per frame, falls back to the sheet's frame_length when not set
+    pub frame_length: Option<f32>,
+    /// Seconds for specific frames by index, overriding frame_length
+    pub frame_durations: HashMap<usize, f32>,
+    pub loop_mode: LoopMode,
+    /// Names sent in an AnimationEvent when playback reaches the frame at that index
+    pub tags: HashMap<usize, String>,
+}
+
+impl Animation {
+    pub fn new(frames: Vec<String>) -> Self {
+        Animation {
+            frames,
+            frame_length: None,
+            frame_durations: HashMap::new(),
+            loop_mode: LoopMode::Loop,
+            tags: HashMap::new(),
+        }
+    }
+
+    pub fn frame_length(mut self, frame_length: f32) -> Self {
+        self.frame_length = Some(frame_length);
+        self
+    }
+
+    pub fn frame_duration(mut self, index: usize, duration: f32) -> Self {
+        self.frame_durations.insert(index, duration);
+        self
+    }
+
+    pub fn loop_mode(mut self, loop_mode: LoopMode) -> Self {
+        self.loop_mode = loop_mode;
+        self
+    }
+
+    pub fn tag(mut self, index: usize, name: &str) -> Self {
+        self.tags.insert(index, name.to_string());
+        self
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum AnimationEventKind {
+    /// Sent at the end of every cycle for looping animations
+    Finished,
+    Tag(String),
+}
+
+pub struct AnimationEvent {
+    pub entity: Entity,
+    pub animation: String,
+    pub kind: AnimationEventKind,
+}
+
+/**
+ * Animations that finished or reached a tagged frame during the last AnimationSystem run
+ */
+#[derive(Default)]
+pub struct AnimationEvents {
+    pub events: Vec<AnimationEvent>,
+}
+
 pub struct AnimationSheet {
-    pub animations: HashMap<String, Vec<String>>,
+    pub animations: HashMap<String, Animation>,
     pub current_animation: String,
     pub current_index: usize,
+    /// Default seconds per frame, for animations without their own
     pub frame_length: f32,
     pub time_passed: f32,
     pub playing: bool,
+    /// Playback rate, 2.0 plays twice as fast
+    pub speed: f32,
+    /// Set once a Once or Hold animation reaches its end, until the animation is changed
+    pub finished: bool,
+    // 1 or -1, for ping pong
+    direction: i32,
 }
 
 impl AnimationSheet {
@@ -19,22 +115,30 @@ impl AnimationSheet {
             frame_length,
             time_passed: 0.0,
             playing: false,
+            speed: 1.0,
+            finished: false,
+            direction: 1,
         }
     }
 
     pub fn add_animation(&mut self, name: String, frames: Vec<String>) {
+        self.add(name, Animation::new(frames));
+    }
+
+    pub fn add(&mut self, name: String, animation: Animation) {
         if self.current_animation == "" {
             self.current_animation = name.clone();
         }
-        self.animations.insert(name, frames);
+        self.animations.insert(name, animation);
     }
 
-    pub fn get_current_animation(&self) -> &Vec<String> {
+    pub fn get_current_animation(&self) -> &Animation {
         self.animations.get(&self.current_animation).unwrap()
     }
 
     pub fn get_current_frame(&self) -> &String {
         self.get_current_animation()
+            .frames
             .get(self.current_index)
             .unwrap()
     }
@@ -45,9 +149,320 @@ impl AnimationSheet {
         }
         self.current_animation = name.to_owned();
         self.current_index = 0;
+        self.time_passed = 0.0;
+        self.finished = false;
+        self.direction = 1;
+    }
+
+    /// Jumps to the frame, clamped to the current animation
+    pub fn set_frame(&mut self, index: usize) {
+        let frame_count = self.get_current_animation().frames.len();
+        self.current_index = index.min(frame_count.saturating_sub(1));
+        self.time_passed = 0.0;
+    }
+
+    /// How long the current frame shows for, before speed is applied
+    pub fn get_frame_length(&self) -> f32 {
+        let animation = self.get_current_animation();
+        animation
+            .frame_durations
+            .get(&self.current_index)
+            .cloned()
+            .or(animation.frame_length)
+            .unwrap_or(self.frame_length)
+    }
+
+    /**
+     * Moves playback along by dt seconds, returning any events it caused in order
+     */
+    pub fn update(&mut self, dt: f32) -> Vec<AnimationEventKind> {
+        let mut events = Vec::new();
+        if !self.playing || self.finished {
+            return events;
+        }
+
+        self.time_passed += dt * self.speed;
+        loop {
+            // a zero length frame would never let the loop end
+            let frame_length = self.get_frame_length().max(0.001);
+            if self.time_passed < frame_length || self.finished {
+                break;
+            }
+            self.time_passed -= frame_length;
+            self.advance(&mut events);
+        }
+
+        events
+    }
+
+    fn advance(&mut self, events: &mut Vec<AnimationEventKind>) {
+        let (frame_count, loop_mode) = {
+            let animation = self.get_current_animation();
+            (animation.frames.len(), animation.loop_mode)
+        };
+        let last = frame_count.saturating_sub(1);
+
+        let next_index = match loop_mode {
+            LoopMode::Loop => {
+                if self.current_index >= last {
+                    events.push(AnimationEventKind::Finished);
+                    0
+                } else {
+                    self.current_index + 1
+                }
+            }
+            LoopMode::Once | LoopMode::Hold => {
+                if self.current_index >= last {
+                    events.push(AnimationEventKind::Finished);
+                    self.finished = true;
+                    self.time_passed = 0.0;
+                    if loop_mode == LoopMode::Once {
+                        0
+                    } else {
+                        last
+                    }
+                } else {
+                    self.current_index + 1
+                }
+            }
+            LoopMode::PingPong => {
+                if last == 0 {
+                    events.push(AnimationEventKind::Finished);
+                    0
+                } else {
+                    let next = self.current_index as i32 + self.direction;
+                    if next < 0 || next > last as i32 {
+                        self.direction = -self.direction;
+                    }
+                    let next = self.current_index as i32 + self.direction;
+                    // a cycle ends back on the first frame
+                    if next == 0 {
+                        events.push(AnimationEventKind::Finished);
+                    }
+                    next as usize
+                }
+            }
+        };
+
+        if next_index != self.current_index {
+            self.current_index = next_index;
+            // Once rewinding to the start isn't reaching that frame
+            if !self.finished {
+                if let Some(tag) = self.get_current_animation().tags.get(&next_index) {
+                    events.push(AnimationEventKind::Tag(tag.clone()));
+                }
+            }
+        }
     }
 }
 
 impl Component for AnimationSheet {
     type Storage = VecStorage<AnimationSheet>;
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sheet(animation: Animation) -> AnimationSheet {
+        let mut sheet = AnimationSheet::new(1.0);
+        sheet.add("walk".to_string(), animation);
+        sheet.playing = true;
+        sheet
+    }
+
+    fn frames(count: usize) -> Vec<String> {
+        (0..count).map(|i| format!("frame_{}", i)).collect()
+    }
+
+    // steps one frame length at a time, collecting the index after each step
+    fn indices(sheet: &mut AnimationSheet, steps: usize) -> Vec<usize> {
+        (0..steps)
+            .map(|_| {
+                sheet.update(1.0);
+                sheet.current_index
+            })
+            .collect()
+    }
+
+    #[test]
+    fn loop_wraps_and_finishes_every_cycle() {
+        let mut sheet = sheet(Animation::new(frames(3)));
+
+        assert!(sheet.update(1.0).is_empty());
+        assert_eq!(sheet.current_index, 1);
+        assert!(sheet.update(1.0).is_empty());
+        assert_eq!(sheet.update(1.0), vec![AnimationEventKind::Finished]);
+        assert_eq!(sheet.current_index, 0);
+        assert_eq!(indices(&mut sheet, 4), vec![1, 2, 0, 1]);
+        assert!(!sheet.finished);
+    }
+
+    #[test]
+    fn once_rewinds_and_stops() {
+        let mut sheet = sheet(Animation::new(frames(3)).loop_mode(LoopMode::Once));
+
+        assert_eq!(indices(&mut sheet, 2), vec![1, 2]);
+        assert_eq!(sheet.update(1.0), vec![AnimationEventKind::Finished]);
+        assert_eq!(sheet.current_index, 0);
+        assert!(sheet.finished);
+        assert!(sheet.update(5.0).is_empty());
+        assert_eq!(sheet.current_index, 0);
+    }
+
+    #[test]
+    fn hold_stays_on_last_frame() {
+        let mut sheet = sheet(Animation::new(frames(3)).loop_mode(LoopMode::Hold));
+
+        assert_eq!(sheet.update(10.0), vec![AnimationEventKind::Finished]);
+        assert_eq!(sheet.current_index, 2);
+        assert!(sheet.finished);
+        assert!(sheet.update(1.0).is_empty());
+        assert_eq!(sheet.current_index, 2);
+    }
+
+    #[test]
+    fn ping_pong_turns_without_repeating_end_frames() {
+        let mut sheet = sheet(Animation::new(frames(3)).loop_mode(LoopMode::PingPong));
+
+        assert_eq!(indices(&mut sheet, 8), vec![1, 2, 1, 0, 1, 2, 1, 0]);
+        assert!(!sheet.finished);
+    }
+
+    #[test]
+    fn ping_pong_finishes_back_on_first_frame() {
+        let mut sheet = sheet(Animation::new(frames(3)).loop_mode(LoopMode::PingPong));
+
+        assert!(sheet.update(3.0).is_empty());
+        assert_eq!(sheet.current_index, 1);
+        assert_eq!(sheet.update(1.0), vec![AnimationEventKind::Finished]);
+        assert_eq!(sheet.current_index, 0);
+    }
+
+    #[test]
+    fn ping_pong_with_two_frames() {
+        let mut sheet = sheet(Animation::new(frames(2)).loop_mode(LoopMode::PingPong));
+
+        assert_eq!(indices(&mut sheet, 4), vec![1, 0, 1, 0]);
+    }
+
+    #[test]
+    fn single_frame_ping_pong_only_finishes() {
+        let mut sheet = sheet(Animation::new(frames(1)).loop_mode(LoopMode::PingPong));
+
+        assert_eq!(sheet.update(1.0), vec![AnimationEventKind::Finished]);
+        assert_eq!(sheet.current_index, 0);
+    }
+
+    #[test]
+    fn tags_are_sent_on_reaching_the_frame() {
+        let mut sheet = sheet(Animation::new(frames(3)).tag(0, "start").tag(2, "step"));
+
+        assert!(sheet.update(1.0).is_empty());
+        assert_eq!(
+            sheet.update(1.0),
+            vec![AnimationEventKind::Tag("step".to_string())]
+        );
+        assert_eq!(
+            sheet.update(1.0),
+            vec![
+                AnimationEventKind::Finished,
+                AnimationEventKind::Tag("start".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn once_rewinding_does_not_tag_the_first_frame() {
+        let mut sheet = sheet(
+            Animation::new(frames(2))
+                .loop_mode(LoopMode::Once)
+                .tag(0, "start"),
+        );
+
+        assert_eq!(sheet.update(2.0), vec![AnimationEventKind::Finished]);
+        assert_eq!(sheet.current_index, 0);
+    }
+
+    #[test]
+    fn one_update_can_pass_several_frames() {
+        let mut sheet = sheet(Animation::new(frames(3)).tag(1, "step"));
+
+        assert_eq!(
+            sheet.update(3.5),
+            vec![
+                AnimationEventKind::Tag("step".to_string()),
+                AnimationEventKind::Finished,
+            ]
+        );
+        assert_eq!(sheet.current_index, 0);
+        assert_eq!(sheet.time_passed, 0.5);
+    }
+
+    #[test]
+    fn frame_durations_override_frame_length() {
+        let mut sheet = sheet(
+            Animation::new(frames(3))
+                .frame_length(0.5)
+                .frame_duration(1, 2.0),
+        );
+
+        sheet.update(0.5);
+        assert_eq!(sheet.current_index, 1);
+        sheet.update(1.5);
+        assert_eq!(sheet.current_index, 1);
+        sheet.update(0.5);
+        assert_eq!(sheet.current_index, 2);
+    }
+
+    #[test]
+    fn zero_length_frame_is_passed_through() {
+        let mut sheet = sheet(Animation::new(frames(3)).frame_duration(1, 0.0));
+
+        sheet.update(1.0);
+        assert_eq!(sheet.current_index, 1);
+        sheet.update(0.5);
+        assert_eq!(sheet.current_index, 2);
+    }
+
+    #[test]
+    fn zero_length_animation_does_not_hang() {
+        let mut sheet = sheet(Animation::new(frames(2)).frame_length(0.0));
+
+        let events = sheet.update(0.01);
+        assert!(events.contains(&AnimationEventKind::Finished));
+        assert!(sheet.time_passed < 0.001);
+    }
+
+    #[test]
+    fn paused_sheet_stays_put() {
+        let mut sheet = sheet(Animation::new(frames(3)));
+        sheet.playing = false;
+
+        assert!(sheet.update(5.0).is_empty());
+        assert_eq!(sheet.current_index, 0);
+    }
+
+    #[test]
+    fn speed_scales_playback() {
+        let mut sheet = sheet(Animation::new(frames(3)));
+        sheet.speed = 2.0;
+
+        sheet.update(0.5);
+        assert_eq!(sheet.current_index, 1);
+    }
+
+    #[test]
+    fn changing_animation_resets_playback() {
+        let mut sheet = sheet(Animation::new(frames(2)).loop_mode(LoopMode::Once));
+        sheet.add("idle".to_string(), Animation::new(frames(2)));
+        sheet.update(2.0);
+        assert!(sheet.finished);
+
+        sheet.set_current_animation("idle");
+        assert!(!sheet.finished);
+        assert_eq!(sheet.current_index, 0);
+        assert!(sheet.update(1.0).is_empty());
+        assert_eq!(sheet.current_index, 1);
+    }
+}
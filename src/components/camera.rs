@@ -1,3 +1,28 @@
-use cgmath::Matrix4;
+use cgmath::{Matrix4, Vector2, Vector3};
+
+use crate::renderer::get_ortho;
 
 pub struct Camera(pub Matrix4<f32>);
+
+impl Camera {
+    /// World position at the top left of the screen
+    pub fn position(&self) -> Vector2<f32> {
+        let m = &self.0;
+        // the inverse of the projection for the clip space corner at -1, 1
+        Vector2::new((-1.0 - m.w.x) / m.x.x, (1.0 - m.w.y) / m.y.y)
+    }
+
+    /// Moves the camera so the world position is at the top left of the screen
+    pub fn set_position(&mut self, x: f32, y: f32) {
+        self.0 = get_ortho() * Matrix4::from_translation(Vector3::new(-x, -y, 0.0));
+    }
+}
+
+/**
+ * Size of the current map in pixels. The camera stays inside it, and so does the player
+ */
+#[derive(Default)]
+pub struct CameraBounds {
+    pub w: f32,
+    pub h: f32,
+}
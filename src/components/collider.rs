@@ -0,0 +1,73 @@
+use cgmath::Vector2;
+use specs::{Component, VecStorage};
+
+use crate::components::Shape;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum ColliderShape {
+    Rect {
+        w: f32,
+        h: f32,
+    },
+    Circle {
+        radius: f32,
+    },
+    /// Closed, points relative to the collider's position
+    Polygon(Vec<Vector2<f32>>),
+    /// Open chain of edges, for walls that don't enclose anything
+    Polyline(Vec<Vector2<f32>>),
+}
+
+/**
+ * Collision geometry placed offset from the entity's position. Circles are centered on the
+ * offset, rectangles have their top left corner there. Sensors report overlaps without
+ * blocking movement, for things like spell areas.
+ */
+pub struct Collider {
+    pub shape: ColliderShape,
+    pub offset: Vector2<f32>,
+    pub sensor: bool,
+}
+
+impl Collider {
+    pub fn new(shape: ColliderShape) -> Self {
+        Collider {
+            shape,
+            offset: Vector2::new(0.0, 0.0),
+            sensor: false,
+        }
+    }
+
+    pub fn offset(mut self, x: f32, y: f32) -> Self {
+        self.offset = Vector2::new(x, y);
+        self
+    }
+
+    pub fn sensor(mut self, sensor: bool) -> Self {
+        self.sensor = sensor;
+        self
+    }
+
+    /// Outline of the collider for drawing, positioned relative to the offset
+    pub fn to_shape(&self, color: [f32; 4]) -> Shape {
+        match &self.shape {
+            ColliderShape::Rect { w, h } => Shape::new(
+                vec![
+                    Vector2::new(0.0, 0.0),
+                    Vector2::new(*w, 0.0),
+                    Vector2::new(*w, *h),
+                    Vector2::new(0.0, *h),
+                ],
+                color,
+                false,
+            ),
+            ColliderShape::Circle { radius } => Shape::circle(*radius, color, false),
+            ColliderShape::Polygon(points) => Shape::new(points.clone(), color, false),
+            ColliderShape::Polyline(points) => Shape::polyline(points.clone(), color),
+        }
+    }
+}
+
+impl Component for Collider {
+    type Storage = VecStorage<Self>;
+}
@@ -0,0 +1,18 @@
+/**
+ * Outlines every transform and node in the scene, and shows the FrameStats. Toggled with the
+ * "debug" key, see world::render_debug_overlay for what gets drawn.
+ */
+#[derive(Default)]
+pub struct DebugOverlay {
+    pub enabled: bool,
+}
+
+impl DebugOverlay {
+    pub fn new() -> Self {
+        DebugOverlay { enabled: false }
+    }
+
+    pub fn toggle(&mut self) {
+        self.enabled = !self.enabled;
+    }
+}
@@ -0,0 +1,317 @@
+use cgmath::Vector2;
+use specs::{Component, VecStorage};
+
+use crate::components::AnimationSheet;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Direction {
+    N,
+    NE,
+    E,
+    SE,
+    S,
+    SW,
+    W,
+    NW,
+}
+
+impl Direction {
+    /// Suffix used in animation names, like walk_ne
+    pub fn get_suffix(&self) -> &'static str {
+        match self {
+            Direction::N => "n",
+            Direction::NE => "ne",
+            Direction::E => "e",
+            Direction::SE => "se",
+            Direction::S => "s",
+            Direction::SW => "sw",
+            Direction::W => "w",
+            Direction::NW => "nw",
+        }
+    }
+
+    /// Flipped horizontally, north and south stay the same
+    pub fn mirror(&self) -> Direction {
+        match self {
+            Direction::NE => Direction::NW,
+            Direction::E => Direction::W,
+            Direction::SE => Direction::SW,
+            Direction::SW => Direction::SE,
+            Direction::W => Direction::E,
+            Direction::NW => Direction::NE,
+            direction => *direction,
+        }
+    }
+
+    /// The horizontal and vertical directions a diagonal is made of
+    fn get_cardinals(&self) -> Option<(Direction, Direction)> {
+        match self {
+            Direction::NE => Some((Direction::E, Direction::N)),
+            Direction::SE => Some((Direction::E, Direction::S)),
+            Direction::SW => Some((Direction::W, Direction::S)),
+            Direction::NW => Some((Direction::W, Direction::N)),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum FacingMode {
+    Four,
+    Eight,
+}
+
+/**
+ * Which way a character looks, for picking directional animations. Follows the entity's
+ * movement through FacingSystem, or can be pointed somewhere with face.
+ */
+pub struct Facing {
+    pub direction: Direction,
+    pub mode: FacingMode,
+    /// Set to false to only change direction through face, for aiming
+    pub follow_movement: bool,
+    pub last_pos: Option<Vector2<f32>>,
+}
+
+impl Facing {
+    pub fn new(mode: FacingMode) -> Self {
+        Facing {
+            direction: Direction::S,
+            mode,
+            follow_movement: true,
+            last_pos: None,
+        }
+    }
+
+    /**
+     * Turns towards the vector, y being down. A zero vector keeps the current direction.
+     * When four way, diagonals keep the current direction if it's one of the two options.
+     */
+    pub fn face(&mut self, x: f32, y: f32) {
+        if x == 0.0 && y == 0.0 {
+            return;
+        }
+
+        self.direction = match self.mode {
+            FacingMode::Eight => {
+                let angle = y.atan2(x).to_degrees();
+                // 0 is east, going clockwise as y is down
+                let octant = ((angle + 360.0 + 22.5) / 45.0) as i32 % 8;
+                match octant {
+                    0 => Direction::E,
+                    1 => Direction::SE,
+                    2 => Direction::S,
+                    3 => Direction::SW,
+                    4 => Direction::W,
+                    5 => Direction::NW,
+                    6 => Direction::N,
+                    _ => Direction::NE,
+                }
+            }
+            FacingMode::Four => {
+                let horizontal = if x > 0.0 { Direction::E } else { Direction::W };
+                let vertical = if y > 0.0 { Direction::S } else { Direction::N };
+                if x.abs() > y.abs() {
+                    horizontal
+                } else if y.abs() > x.abs() {
+                    vertical
+                } else if self.direction == vertical {
+                    vertical
+                } else {
+                    horizontal
+                }
+            }
+        };
+    }
+
+    /**
+     * Finds the animation for base facing this way, going by the base_direction naming
+     * convention. Falls back to the mirrored direction, then for diagonals to the horizontal
+     * and vertical directions, then to base itself. Returns the name and whether to flip.
+     */
+    pub fn get_animation(
+        &self,
+        base: &str,
+        animation_sheet: &AnimationSheet,
+    ) -> Option<(String, bool)> {
+        let mut candidates = vec![self.direction];
+        if let Some((horizontal, vertical)) = self.direction.get_cardinals() {
+            candidates.push(horizontal);
+            candidates.push(vertical);
+        }
+
+        for direction in candidates {
+            for (direction, flip) in &[(direction, false), (direction.mirror(), true)] {
+                let name = format!("{}_{}", base, direction.get_suffix());
+                if animation_sheet.animations.contains_key(&name) {
+                    return Some((name, *flip));
+                }
+            }
+        }
+
+        if animation_sheet.animations.contains_key(base) {
+            Some((base.to_string(), false))
+        } else {
+            None
+        }
+    }
+}
+
+impl Component for Facing {
+    type Storage = VecStorage<Self>;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn facing(mode: FacingMode, direction: Direction) -> Facing {
+        let mut facing = Facing::new(mode);
+        facing.direction = direction;
+        facing
+    }
+
+    fn sheet(names: &[&str]) -> AnimationSheet {
+        let mut sheet = AnimationSheet::new(0.1);
+        for name in names {
+            sheet.add_animation(name.to_string(), vec![format!("{}_0", name)]);
+        }
+        sheet
+    }
+
+    fn animation(direction: Direction, sheet: &AnimationSheet) -> Option<(String, bool)> {
+        facing(FacingMode::Eight, direction).get_animation("walk", sheet)
+    }
+
+    #[test]
+    fn eight_way_octants() {
+        let cases = [
+            ((1.0, 0.0), Direction::E),
+            ((1.0, 1.0), Direction::SE),
+            ((0.0, 1.0), Direction::S),
+            ((-1.0, 1.0), Direction::SW),
+            ((-1.0, 0.0), Direction::W),
+            ((-1.0, -1.0), Direction::NW),
+            ((0.0, -1.0), Direction::N),
+            ((1.0, -1.0), Direction::NE),
+        ];
+
+        for ((x, y), expected) in cases.iter() {
+            let mut facing = Facing::new(FacingMode::Eight);
+            facing.face(*x, *y);
+            assert_eq!(facing.direction, *expected, "facing {}, {}", x, y);
+        }
+    }
+
+    #[test]
+    fn eight_way_splits_octants_halfway_between_directions() {
+        let mut facing = Facing::new(FacingMode::Eight);
+
+        // about 21.8 and 24.2 degrees below east
+        facing.face(10.0, 4.0);
+        assert_eq!(facing.direction, Direction::E);
+        facing.face(10.0, 4.5);
+        assert_eq!(facing.direction, Direction::SE);
+
+        // the same either side of west, where the angle wraps around
+        facing.face(-10.0, -4.0);
+        assert_eq!(facing.direction, Direction::W);
+        facing.face(-10.0, 4.0);
+        assert_eq!(facing.direction, Direction::W);
+        facing.face(-10.0, -4.5);
+        assert_eq!(facing.direction, Direction::NW);
+    }
+
+    #[test]
+    fn four_way_picks_the_larger_axis() {
+        let mut facing = Facing::new(FacingMode::Four);
+
+        facing.face(1.0, 0.5);
+        assert_eq!(facing.direction, Direction::E);
+        facing.face(-0.5, 1.0);
+        assert_eq!(facing.direction, Direction::S);
+        facing.face(-1.0, -0.5);
+        assert_eq!(facing.direction, Direction::W);
+        facing.face(0.5, -1.0);
+        assert_eq!(facing.direction, Direction::N);
+    }
+
+    #[test]
+    fn four_way_diagonals_keep_a_matching_direction() {
+        let mut facing = facing(FacingMode::Four, Direction::S);
+        facing.face(1.0, 1.0);
+        assert_eq!(facing.direction, Direction::S);
+
+        let mut facing = self::facing(FacingMode::Four, Direction::E);
+        facing.face(1.0, 1.0);
+        assert_eq!(facing.direction, Direction::E);
+
+        // neither option, so horizontal wins
+        let mut facing = self::facing(FacingMode::Four, Direction::N);
+        facing.face(1.0, 1.0);
+        assert_eq!(facing.direction, Direction::E);
+    }
+
+    #[test]
+    fn zero_vector_keeps_direction() {
+        let mut facing = facing(FacingMode::Eight, Direction::NW);
+        facing.face(0.0, 0.0);
+        assert_eq!(facing.direction, Direction::NW);
+    }
+
+    #[test]
+    fn own_direction_wins_over_mirror() {
+        let sheet = sheet(&["walk_e", "walk_w"]);
+
+        assert_eq!(
+            animation(Direction::W, &sheet),
+            Some(("walk_w".to_string(), false))
+        );
+    }
+
+    #[test]
+    fn falls_back_to_mirrored_direction() {
+        let sheet = sheet(&["walk_e", "walk_nw"]);
+
+        assert_eq!(
+            animation(Direction::W, &sheet),
+            Some(("walk_e".to_string(), true))
+        );
+        assert_eq!(
+            animation(Direction::NE, &sheet),
+            Some(("walk_nw".to_string(), true))
+        );
+    }
+
+    #[test]
+    fn diagonals_fall_back_to_horizontal_then_vertical() {
+        let sheet = sheet(&["walk_e", "walk_n"]);
+
+        assert_eq!(
+            animation(Direction::NE, &sheet),
+            Some(("walk_e".to_string(), false))
+        );
+        assert_eq!(
+            animation(Direction::SW, &sheet),
+            Some(("walk_e".to_string(), true))
+        );
+
+        let sheet = self::sheet(&["walk_n"]);
+        assert_eq!(
+            animation(Direction::NW, &sheet),
+            Some(("walk_n".to_string(), false))
+        );
+    }
+
+    #[test]
+    fn falls_back_to_base_then_none() {
+        let sheet = sheet(&["walk"]);
+        assert_eq!(
+            animation(Direction::S, &sheet),
+            Some(("walk".to_string(), false))
+        );
+
+        let sheet = self::sheet(&["idle_s"]);
+        assert_eq!(animation(Direction::S, &sheet), None);
+    }
+}
@@ -0,0 +1,84 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+/**
+ * What the renderer did over a frame, see Renderer::take_stats
+ */
+#[derive(Clone, Copy, Debug, Default)]
+pub struct RenderStats {
+    pub draw_calls: u32,
+    pub vertices: u32,
+    /// Flushes caused by switching to another sheet, rather than a material change or the end
+    /// of the frame
+    pub sheet_flushes: u32,
+    pub buffers_created: u32,
+}
+
+/**
+ * Numbers for the previous frame, filled in by the main loop and the Timed systems.
+ * Times are in seconds.
+ */
+#[derive(Default)]
+pub struct FrameStats {
+    pub frame_time: f32,
+    /// Time spent walking the scene graph and building batches
+    pub render_time: f32,
+    pub render: RenderStats,
+    pub entity_count: usize,
+    // systems run in parallel, so they record through a shared reference
+    system_times: Mutex<HashMap<String, f32>>,
+}
+
+impl FrameStats {
+    pub fn new() -> Self {
+        FrameStats::default()
+    }
+
+    pub fn record_system(&self, name: &str, seconds: f32) {
+        let mut system_times = self.system_times.lock().unwrap();
+        system_times.insert(name.to_string(), seconds);
+    }
+
+    /// Sorted by name, so the order is stable between frames
+    pub fn get_system_times(&self) -> Vec<(String, f32)> {
+        let system_times = self.system_times.lock().unwrap();
+        let mut system_times: Vec<(String, f32)> = system_times
+            .iter()
+            .map(|(name, seconds)| (name.clone(), *seconds))
+            .collect();
+        system_times.sort_by(|a, b| a.0.cmp(&b.0));
+        system_times
+    }
+
+    pub fn csv_header(&self) -> String {
+        let mut columns = vec![
+            "frame_time".to_string(),
+            "render_time".to_string(),
+            "draw_calls".to_string(),
+            "vertices".to_string(),
+            "sheet_flushes".to_string(),
+            "buffers_created".to_string(),
+            "entity_count".to_string(),
+        ];
+        columns.extend(self.get_system_times().into_iter().map(|(name, _)| name));
+        columns.join(",")
+    }
+
+    pub fn csv_row(&self) -> String {
+        let mut columns = vec![
+            self.frame_time.to_string(),
+            self.render_time.to_string(),
+            self.render.draw_calls.to_string(),
+            self.render.vertices.to_string(),
+            self.render.sheet_flushes.to_string(),
+            self.render.buffers_created.to_string(),
+            self.entity_count.to_string(),
+        ];
+        columns.extend(
+            self.get_system_times()
+                .into_iter()
+                .map(|(_, seconds)| seconds.to_string()),
+        );
+        columns.join(",")
+    }
+}
@@ -0,0 +1,50 @@
+use specs::{Component, VecStorage};
+
+/**
+ * A point light, drawn into the renderer's light map centered on the entity
+ */
+pub struct Light {
+    pub color: [f32; 3],
+    pub radius: f32,
+    pub intensity: f32,
+    /// How much of the intensity flicker can take away, 0 to 1
+    pub flicker: f32,
+    pub flicker_speed: f32,
+    pub current_intensity: f32,
+    pub time_passed: f32,
+}
+
+impl Light {
+    pub fn new(color: [f32; 3], radius: f32, intensity: f32) -> Self {
+        Light {
+            color,
+            radius,
+            intensity,
+            flicker: 0.0,
+            flicker_speed: 0.0,
+            current_intensity: intensity,
+            time_passed: 0.0,
+        }
+    }
+
+    pub fn flicker(mut self, flicker: f32, flicker_speed: f32) -> Self {
+        self.flicker = flicker;
+        self.flicker_speed = flicker_speed;
+        self
+    }
+}
+
+impl Component for Light {
+    type Storage = VecStorage<Self>;
+}
+
+/**
+ * Light level of anything not lit by a Light. White leaves the scene as is
+ */
+pub struct AmbientLight(pub [f32; 4]);
+
+impl Default for AmbientLight {
+    fn default() -> Self {
+        AmbientLight([1.0; 4])
+    }
+}
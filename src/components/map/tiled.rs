@@ -1,96 +1,250 @@
 use specs::{Component, HashMapStorage};
-use tiled;
+use tiled::{self, PropertyValue};
 
 use crate::renderer::Vertex;
 
+/**
+ * A single tile layer of a map. Visibility is handled by the Transform of the layer's entity,
+ * so layers can be toggled like any other node.
+ */
 pub struct TiledMap {
+    pub name: String,
     pub data: Vec<Vertex>,
     // assuming usage of one tileset for now
     pub tileset: String,
+    pub opacity: f32,
+    pub tint: [f32; 4],
 }
 
 impl TiledMap {
-    pub fn new(tilemap: &tiled::Map) -> TiledMap {
-        let mut vertex_data: Vec<Vertex> = Vec::new();
-
+    pub fn new(tilemap: &tiled::Map, layer: &tiled::Layer) -> TiledMap {
         let mut tileset_source = String::new();
+        let tint = if let Some(PropertyValue::ColorValue(argb)) = layer.properties.get("tint") {
+            color_from_argb(*argb)
+        } else {
+            [1.0; 4]
+        };
+
+        let mut tiled_map = TiledMap {
+            name: layer.name.clone(),
+            data: Self::build_layer(tilemap, layer, &mut tileset_source),
+            tileset: tileset_source,
+            opacity: layer.opacity,
+            tint,
+        };
+        tiled_map.update_color();
+
+        tiled_map
+    }
+
+    /**
+     * A single tile placed as an object, drawn as one w by h quad from the entity's position,
+     * the same way as the tile would be in a layer. None when no tileset has the tile
+     */
+    pub fn from_tile_object(
+        tilemap: &tiled::Map,
+        name: &str,
+        gid: u32,
+        w: f32,
+        h: f32,
+    ) -> Option<TiledMap> {
+        let (tileset, uvs) = get_tile_region(tilemap, gid)?;
+
+        let mut data = Vec::with_capacity(4);
+        push_tile_quad(&mut data, 0.0, 0.0, w, h, uvs, gid);
+
+        Some(TiledMap {
+            name: name.to_string(),
+            data,
+            tileset,
+            opacity: 1.0,
+            tint: [1.0; 4],
+        })
+    }
+
+    /**
+     * The picture of an image layer as one quad at its full size. Layers without an image
+     * have nothing to draw, so get no TiledMap
+     */
+    pub fn from_image_layer(layer: &tiled::ImageLayer) -> Option<TiledMap> {
+        let image = layer.image.as_ref()?;
+        let w = image.width as f32;
+        let h = image.height as f32;
+        let tint = if let Some(PropertyValue::ColorValue(argb)) = layer.properties.get("tint") {
+            color_from_argb(*argb)
+        } else {
+            [1.0; 4]
+        };
+
+        let mut data = Vec::with_capacity(4);
+        push_tile_quad(&mut data, 0.0, 0.0, w, h, [0.0, 0.0, 1.0, 1.0], 0);
+
+        let mut tiled_map = TiledMap {
+            name: layer.name.clone(),
+            data,
+            tileset: image.source.clone(),
+            opacity: layer.opacity,
+            tint,
+        };
+        tiled_map.update_color();
+
+        Some(tiled_map)
+    }
+
+    pub fn set_opacity(&mut self, opacity: f32) {
+        self.opacity = opacity.max(0.0).min(1.0);
+        self.update_color();
+    }
+
+    pub fn set_tint(&mut self, tint: [f32; 4]) {
+        self.tint = tint;
+        self.update_color();
+    }
+
+    pub fn get_color(&self) -> [f32; 4] {
+        [
+            self.tint[0],
+            self.tint[1],
+            self.tint[2],
+            self.tint[3] * self.opacity,
+        ]
+    }
+
+    fn update_color(&mut self) {
+        let color = self.get_color();
+        for vertex in &mut self.data {
+            vertex.color = color;
+        }
+    }
+
+    // positions are local to the layer with y going down like in Tiled, so the offsets on the
+    // layer entity's transform move it the same way Tiled does
+    fn build_layer(
+        tilemap: &tiled::Map,
+        layer: &tiled::Layer,
+        tileset_source: &mut String,
+    ) -> Vec<Vertex> {
+        let mut vertex_data: Vec<Vertex> = Vec::new();
 
-        let mut index = 0;
-        for (layer_index, layer) in tilemap.layers.iter().enumerate() {
-            let layer_z = layer_index as f32 + 1.0;
-            for (row, cols) in layer.tiles.iter().enumerate() {
-                for (col, cell) in cols.iter().enumerate() {
-                    if *cell != 0 {
-                        let x = col as f32 * tilemap.tile_width as f32;
-                        let y = (tilemap.tile_height * tilemap.height) as f32
-                            - (row as f32 * tilemap.tile_height as f32)
-                            - tilemap.tile_height as f32;
-                        let w = tilemap.tile_width as f32;
-                        let h = tilemap.tile_height as f32;
-                        vertex_data.push(Vertex {
-                            pos: [x, y, layer_z],
-                            uv: [0.0, 0.0],
-                            color: [1.0, 1.0, 1.0, 1.0],
-                        });
-                        vertex_data.push(Vertex {
-                            pos: [x + w, y, layer_z],
-                            uv: [0.0, 0.0],
-                            color: [1.0, 1.0, 1.0, 1.0],
-                        });
-                        vertex_data.push(Vertex {
-                            pos: [x + w, y + h, layer_z],
-                            uv: [0.0, 0.0],
-                            color: [1.0, 1.0, 1.0, 1.0],
-                        });
-                        vertex_data.push(Vertex {
-                            pos: [x, y + h, layer_z],
-                            uv: [0.0, 0.0],
-                            color: [1.0, 1.0, 1.0, 1.0],
-                        });
-
-                        // build out texture coord data
-                        for tileset in tilemap.tilesets.iter() {
-                            let image = &tileset.images[0];
-                            tileset_source = image.source.clone();
-                            // just handling a single image for now
-                            if tileset.first_gid as usize + tileset.tiles.len() - 1
-                                <= *cell as usize
-                            {
-                                let iw = image.width as u32;
-                                let ih = image.height as u32;
-                                let tiles_wide = iw / (tileset.tile_width + tileset.spacing);
-                                let tiles_high = ih / (tileset.tile_height + tileset.spacing);
-                                let tile_width_uv = tileset.tile_width as f32 / iw as f32;
-                                let tile_height_uv = tileset.tile_height as f32 / ih as f32;
-                                let x = ((*cell as u32 - 1u32) % tiles_wide) as f32
-                                    + tileset.margin as f32 / iw as f32;
-                                let y = ((*cell as u32 - 1u32) / tiles_wide) as f32
-                                    + tileset.margin as f32 / ih as f32;
-                                let tiles_wide = tiles_wide as f32;
-                                let tiles_high = tiles_high as f32;
-                                vertex_data[index].uv[0] = x / tiles_wide;
-                                vertex_data[index].uv[1] = y / tiles_high + tile_height_uv;
-                                vertex_data[index + 1].uv[0] = x / tiles_wide + tile_width_uv;
-                                vertex_data[index + 1].uv[1] = y / tiles_high + tile_height_uv;
-                                vertex_data[index + 2].uv[0] = x / tiles_wide + tile_width_uv;
-                                vertex_data[index + 2].uv[1] = y / tiles_high;
-                                vertex_data[index + 3].uv[0] = x / tiles_wide;
-                                vertex_data[index + 3].uv[1] = y / tiles_high;
-                                break;
-                            }
-                        }
-
-                        index += 4;
+        for (row, cols) in layer.tiles.iter().enumerate() {
+            for (col, cell) in cols.iter().enumerate() {
+                if *cell != 0 {
+                    if let Some((source, uvs)) = get_tile_region(tilemap, *cell) {
+                        *tileset_source = source;
+                        push_tile_quad(
+                            &mut vertex_data,
+                            col as f32 * tilemap.tile_width as f32,
+                            row as f32 * tilemap.tile_height as f32,
+                            tilemap.tile_width as f32,
+                            tilemap.tile_height as f32,
+                            uvs,
+                            *cell,
+                        );
                     }
                 }
             }
         }
 
-        TiledMap {
-            data: vertex_data,
-            tileset: tileset_source,
+        vertex_data
+    }
+}
+
+// tiled keeps flip flags in the top bits of a gid
+const FLIPPED_HORIZONTALLY: u32 = 0x8000_0000;
+const FLIPPED_VERTICALLY: u32 = 0x4000_0000;
+const FLIPPED_DIAGONALLY: u32 = 0x2000_0000;
+
+/**
+ * Adds a w by h quad at x, y, clockwise from the top left corner, showing the [x, y, x2, y2]
+ * texture region upright. The gid's flip flags are applied on top, diagonal first like Tiled.
+ */
+fn push_tile_quad(data: &mut Vec<Vertex>, x: f32, y: f32, w: f32, h: f32, uvs: [f32; 4], gid: u32) {
+    let corners = [(0.0, 0.0), (1.0, 0.0), (1.0, 1.0), (0.0, 1.0)];
+    for (corner_x, corner_y) in corners.iter() {
+        let (mut s, mut t) = if gid & FLIPPED_DIAGONALLY != 0 {
+            (*corner_y, *corner_x)
+        } else {
+            (*corner_x, *corner_y)
+        };
+        if gid & FLIPPED_HORIZONTALLY != 0 {
+            s = 1.0 - s;
+        }
+        if gid & FLIPPED_VERTICALLY != 0 {
+            t = 1.0 - t;
+        }
+
+        data.push(Vertex {
+            pos: [x + corner_x * w, y + corner_y * h, 0.0],
+            uv: [
+                uvs[0] + (uvs[2] - uvs[0]) * s,
+                uvs[1] + (uvs[3] - uvs[1]) * t,
+            ],
+            color: [1.0; 4],
+        });
+    }
+}
+
+/**
+ * The image of the tileset holding the gid, and the tile's [x, y, x2, y2] texture coordinates
+ * with y going down the image. Flip flags on the gid are ignored.
+ */
+pub fn get_tile_region(tilemap: &tiled::Map, gid: u32) -> Option<(String, [f32; 4])> {
+    let gid = gid & !(FLIPPED_HORIZONTALLY | FLIPPED_VERTICALLY | FLIPPED_DIAGONALLY);
+
+    for tileset in tilemap.tilesets.iter() {
+        // just handling a single image per tileset for now
+        let image = match tileset.images.get(0) {
+            Some(image) => image,
+            None => continue,
+        };
+        let iw = image.width as u32;
+        let ih = image.height as u32;
+        let step_x = tileset.tile_width + tileset.spacing;
+        let step_y = tileset.tile_height + tileset.spacing;
+        // the margin goes around the whole image, spacing only between tiles
+        let columns = (iw + tileset.spacing).saturating_sub(tileset.margin * 2) / step_x;
+        let rows = (ih + tileset.spacing).saturating_sub(tileset.margin * 2) / step_y;
+
+        if gid < tileset.first_gid || gid >= tileset.first_gid + columns * rows {
+            continue;
         }
+
+        let id = gid - tileset.first_gid;
+        let x = (tileset.margin + (id % columns) * step_x) as f32;
+        let y = (tileset.margin + (id / columns) * step_y) as f32;
+        let iw = iw as f32;
+        let ih = ih as f32;
+
+        return Some((
+            image.source.clone(),
+            [
+                x / iw,
+                y / ih,
+                (x + tileset.tile_width as f32) / iw,
+                (y + tileset.tile_height as f32) / ih,
+            ],
+        ));
     }
+
+    None
+}
+
+/**
+ * Tiled stores colours as #AARRGGBB, converts to the rgba array the renderer uses
+ */
+pub fn color_from_argb(argb: u32) -> [f32; 4] {
+    // tiled omits the alpha when it's fully opaque, so #RRGGBB comes through with 0 alpha
+    let alpha = (argb >> 24) & 0xff;
+    [
+        ((argb >> 16) & 0xff) as f32 / 255.0,
+        ((argb >> 8) & 0xff) as f32 / 255.0,
+        (argb & 0xff) as f32 / 255.0,
+        if alpha == 0 {
+            1.0
+        } else {
+            alpha as f32 / 255.0
+        },
+    ]
 }
 
 impl Component for TiledMap {
@@ -0,0 +1,33 @@
+/**
+ * Set to move the player to the spawn object of another map, see Play::change_map.
+ * Handled after the systems run, so the map is swapped between frames.
+ */
+#[derive(Clone, Default)]
+pub struct MapTransition {
+    pub map: String,
+    /// Name of the object in the target map the player is placed at
+    pub spawn: String,
+}
+
+impl MapTransition {
+    pub fn new() -> MapTransition {
+        MapTransition {
+            map: "".to_string(),
+            spawn: "".to_string(),
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.map = "".to_string();
+        self.spawn = "".to_string();
+    }
+
+    pub fn set(&mut self, map: String, spawn: String) {
+        self.map = map;
+        self.spawn = spawn;
+    }
+
+    pub fn is_pending(&self) -> bool {
+        self.map != ""
+    }
+}
@@ -0,0 +1,81 @@
+use specs::{Component, VecStorage};
+
+pub const DEFAULT_EFFECT: &str = "basic";
+
+/**
+ * Picks which registered shader pipeline an entity is drawn with. The uniform values are
+ * interpreted per effect, see the constructors below and the shaders in renderer/shaders
+ */
+#[derive(Clone, Debug, PartialEq)]
+pub struct Material {
+    pub effect: String,
+    pub color: [f32; 4],
+    pub params: [f32; 4],
+    pub extra: [f32; 4],
+}
+
+impl Material {
+    pub fn new(effect: &str) -> Self {
+        Material {
+            effect: effect.to_string(),
+            color: [1.0; 4],
+            params: [0.0; 4],
+            extra: [0.0; 4],
+        }
+    }
+
+    /// Mixes the sprite towards color by amount, 0 to 1
+    pub fn flash(color: [f32; 4], amount: f32) -> Self {
+        let mut material = Material::new("flash");
+        material.color = color;
+        material.params[0] = amount;
+        material
+    }
+
+    /// Discards pixels below the threshold, with an edge of edge_color edge_width wide
+    pub fn dissolve(threshold: f32, edge_color: [f32; 4], edge_width: f32) -> Self {
+        let mut material = Material::new("dissolve");
+        material.color = edge_color;
+        material.params[0] = threshold;
+        material.params[1] = edge_width;
+        material
+    }
+
+    /// Draws a border of color around opaque pixels, thickness in texels
+    pub fn outline(color: [f32; 4], thickness: f32) -> Self {
+        let mut material = Material::new("outline");
+        material.color = color;
+        material.params[0] = thickness;
+        material
+    }
+
+    /// Replaces pixels within tolerance of from with to
+    pub fn palette_swap(from: [f32; 4], to: [f32; 4], tolerance: f32) -> Self {
+        let mut material = Material::new("palette_swap");
+        material.color = to;
+        material.extra = from;
+        material.params[0] = tolerance;
+        material
+    }
+
+    /// Desaturates the sprite by amount, 0 to 1
+    pub fn grayscale(amount: f32) -> Self {
+        let mut material = Material::new("grayscale");
+        material.params[0] = amount;
+        material
+    }
+
+    pub fn set_amount(&mut self, amount: f32) {
+        self.params[0] = amount;
+    }
+}
+
+impl Default for Material {
+    fn default() -> Self {
+        Material::new(DEFAULT_EFFECT)
+    }
+}
+
+impl Component for Material {
+    type Storage = VecStorage<Self>;
+}
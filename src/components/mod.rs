@@ -1,29 +1,57 @@
+mod animation_controller;
 mod animation_sheet;
 mod camera;
+mod collider;
 mod color;
+mod debug_overlay;
 mod delta_time;
 mod entity_lookup;
+mod facing;
+mod frame_stats;
 mod input;
+mod light;
 mod map;
+mod map_transition;
+mod material;
+mod nine_slice;
 mod node;
+mod parallax;
+mod particle_emitter;
 mod player;
+mod post_processing;
 mod screen_change;
 mod shape;
 mod sprite;
 mod text;
 mod transform;
+mod tween;
+mod warp;
 
+pub use self::animation_controller::*;
 pub use self::animation_sheet::*;
 pub use self::camera::*;
+pub use self::collider::*;
 pub use self::color::*;
+pub use self::debug_overlay::*;
 pub use self::delta_time::*;
 pub use self::entity_lookup::*;
+pub use self::facing::*;
+pub use self::frame_stats::*;
 pub use self::input::*;
+pub use self::light::*;
 pub use self::map::*;
+pub use self::map_transition::*;
+pub use self::material::*;
+pub use self::nine_slice::*;
 pub use self::node::*;
+pub use self::parallax::*;
+pub use self::particle_emitter::*;
 pub use self::player::*;
+pub use self::post_processing::*;
 pub use self::screen_change::*;
 pub use self::shape::*;
 pub use self::sprite::*;
 pub use self::text::*;
 pub use self::transform::*;
+pub use self::tween::*;
+pub use self::warp::*;
@@ -0,0 +1,34 @@
+use specs::{Component, VecStorage};
+
+/**
+ * Draws a frame as nine quads stretched to the Transform size. The corners keep their size,
+ * edges stretch along one axis and the center along both.
+ */
+pub struct NineSlice {
+    pub frame_name: String,
+    pub left: f32,
+    pub top: f32,
+    pub right: f32,
+    pub bottom: f32,
+}
+
+impl NineSlice {
+    pub fn new(frame_name: &str, left: f32, top: f32, right: f32, bottom: f32) -> Self {
+        NineSlice {
+            frame_name: frame_name.to_string(),
+            left,
+            top,
+            right,
+            bottom,
+        }
+    }
+
+    /// Same inset on all four sides
+    pub fn uniform(frame_name: &str, inset: f32) -> Self {
+        NineSlice::new(frame_name, inset, inset, inset, inset)
+    }
+}
+
+impl Component for NineSlice {
+    type Storage = VecStorage<Self>;
+}
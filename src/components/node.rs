@@ -4,11 +4,27 @@ use specs::{Component, Entity, VecStorage, World, WriteStorage};
 
 use crate::components::transform::Transform;
 
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SortMode {
+    /// Sorts by z whenever children are added or removed
+    Depth,
+    /// Sorts by z, then by the bottom edge (y + height) every frame. For top-down scenes where
+    /// actors walk in front of and behind each other.
+    BottomEdge,
+}
+
+impl Default for SortMode {
+    fn default() -> Self {
+        SortMode::Depth
+    }
+}
+
 #[derive(Default)]
 pub struct Node {
     pub entities: Vec<Entity>,
     pub parent: Option<Entity>,
     pub children_dirty: bool,
+    pub sort_mode: SortMode,
 }
 
 impl Node {
@@ -17,6 +33,7 @@ impl Node {
             entities: Vec::new(),
             parent: None,
             children_dirty: false,
+            sort_mode: SortMode::Depth,
         }
     }
 
@@ -25,9 +42,15 @@ impl Node {
             entities: Vec::new(),
             parent: Some(entity),
             children_dirty: false,
+            sort_mode: SortMode::Depth,
         }
     }
 
+    pub fn sort_mode(mut self, sort_mode: SortMode) -> Self {
+        self.sort_mode = sort_mode;
+        self
+    }
+
     pub fn add(&mut self, entity: Entity) {
         self.entities.push(entity);
         self.children_dirty = true;
@@ -58,7 +81,8 @@ impl Node {
             self.children_dirty = true;
         }
 
-        if self.children_dirty {
+        if self.children_dirty || self.sort_mode == SortMode::BottomEdge {
+            let sort_mode = self.sort_mode;
             self.entities.sort_by(|entity_a, entity_b| {
                 let transform_a = if let Some(t) = transform_storage.get(*entity_a) {
                     t
@@ -72,7 +96,18 @@ impl Node {
                     return cmp::Ordering::Greater;
                 };
 
-                (transform_a.get_pos().z as i32).cmp(&(transform_b.get_pos().z as i32))
+                let pos_a = transform_a.get_pos();
+                let pos_b = transform_b.get_pos();
+                let order = (pos_a.z as i32).cmp(&(pos_b.z as i32));
+                if order != cmp::Ordering::Equal || sort_mode == SortMode::Depth {
+                    return order;
+                }
+
+                let bottom_a = pos_a.y + transform_a.size.y as f32;
+                let bottom_b = pos_b.y + transform_b.size.y as f32;
+                bottom_a
+                    .partial_cmp(&bottom_b)
+                    .unwrap_or(cmp::Ordering::Equal)
             });
             self.children_dirty = false;
         }
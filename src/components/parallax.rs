@@ -0,0 +1,27 @@
+use cgmath::Vector2;
+use specs::{Component, HashMapStorage};
+
+/**
+ * Scrolls the entity at a fraction of the camera's movement, see ParallaxSystem. A factor of 1
+ * moves with the scene, 0 stays fixed on screen, and above 1 moves faster for foregrounds.
+ * The origin is where the entity sits while the camera is at 0, 0. A foreground has to be
+ * wider than the map to still cover the screen at the far edge, by the camera's range times
+ * the factor minus 1.
+ */
+pub struct Parallax {
+    pub factor: Vector2<f32>,
+    pub origin: Vector2<f32>,
+}
+
+impl Parallax {
+    pub fn new(factor_x: f32, factor_y: f32, origin_x: f32, origin_y: f32) -> Self {
+        Parallax {
+            factor: Vector2::new(factor_x, factor_y),
+            origin: Vector2::new(origin_x, origin_y),
+        }
+    }
+}
+
+impl Component for Parallax {
+    type Storage = HashMapStorage<Self>;
+}
@@ -0,0 +1,233 @@
+use cgmath::Vector2;
+use gfx;
+use specs::{Component, VecStorage};
+
+use crate::{assets::spritesheet_map::SpritesheetMap, utils::Random};
+
+pub struct Particle {
+    pub pos: Vector2<f32>,
+    pub velocity: Vector2<f32>,
+    pub age: f32,
+    pub lifetime: f32,
+}
+
+impl Particle {
+    /// How far through its life the particle is, 0 to 1
+    pub fn get_progress(&self) -> f32 {
+        (self.age / self.lifetime).min(1.0)
+    }
+}
+
+/**
+ * Spawns and holds its particles, rather than having an entity per particle. Particle
+ * positions are relative to the emitter entity, so they follow it through the scene graph.
+ */
+pub struct ParticleEmitter {
+    pub size: f32,
+    /// Particles per second while emitting
+    pub rate: f32,
+    /// Particles to spawn on the next update, regardless of emitting
+    pub burst: u32,
+    pub lifetime: (f32, f32),
+    pub speed: (f32, f32),
+    /// Range of directions in degrees, 0 being right and 90 down
+    pub angle: (f32, f32),
+    pub gravity: Vector2<f32>,
+    pub start_color: [f32; 4],
+    pub end_color: [f32; 4],
+    pub emitting: bool,
+    pub particles: Vec<Particle>,
+    // set through frames or set_frames, so the regions below can't go stale
+    frames: Vec<String>,
+    // the sheet all the frames are on and their regions, see resolve_frames
+    sheet: Option<String>,
+    regions: Vec<([f32; 4], (f32, f32))>,
+    spawn_accumulator: f32,
+    random: Random,
+}
+
+impl ParticleEmitter {
+    pub fn new(rate: f32, lifetime: (f32, f32)) -> Self {
+        ParticleEmitter {
+            frames: Vec::new(),
+            size: 2.0,
+            rate,
+            burst: 0,
+            lifetime,
+            speed: (20.0, 40.0),
+            angle: (0.0, 360.0),
+            gravity: Vector2::new(0.0, 0.0),
+            start_color: [1.0; 4],
+            end_color: [1.0, 1.0, 1.0, 0.0],
+            emitting: true,
+            particles: Vec::new(),
+            sheet: None,
+            regions: Vec::new(),
+            spawn_accumulator: 0.0,
+            random: Random::from_time(),
+        }
+    }
+
+    pub fn frames(mut self, frames: Vec<String>) -> Self {
+        self.set_frames(frames);
+        self
+    }
+
+    /// Frames the particles step through over their lifetime. Uses a square of size when empty
+    pub fn set_frames(&mut self, frames: Vec<String>) {
+        self.frames = frames;
+        self.sheet = None;
+        self.regions.clear();
+    }
+
+    pub fn get_frames(&self) -> &Vec<String> {
+        &self.frames
+    }
+
+    pub fn size(mut self, size: f32) -> Self {
+        self.size = size;
+        self
+    }
+
+    pub fn speed(mut self, min: f32, max: f32) -> Self {
+        self.speed = (min, max);
+        self
+    }
+
+    pub fn angle(mut self, min: f32, max: f32) -> Self {
+        self.angle = (min, max);
+        self
+    }
+
+    pub fn gravity(mut self, x: f32, y: f32) -> Self {
+        self.gravity = Vector2::new(x, y);
+        self
+    }
+
+    pub fn colors(mut self, start_color: [f32; 4], end_color: [f32; 4]) -> Self {
+        self.start_color = start_color;
+        self.end_color = end_color;
+        self
+    }
+
+    pub fn burst(&mut self, count: u32) {
+        self.burst += count;
+    }
+
+    pub fn is_finished(&self) -> bool {
+        !self.emitting && self.burst == 0 && self.particles.len() == 0
+    }
+
+    pub fn update(&mut self, dt: f32) {
+        for particle in &mut self.particles {
+            particle.age += dt;
+            particle.velocity += self.gravity * dt;
+            particle.pos += particle.velocity * dt;
+        }
+        self.particles
+            .retain(|particle| particle.age < particle.lifetime);
+
+        let mut count = self.burst;
+        self.burst = 0;
+        if self.emitting && self.rate > 0.0 {
+            self.spawn_accumulator += dt * self.rate;
+            count += self.spawn_accumulator.floor() as u32;
+            self.spawn_accumulator = self.spawn_accumulator.fract();
+        }
+
+        for _ in 0..count {
+            self.spawn();
+        }
+    }
+
+    fn spawn(&mut self) {
+        let angle = self.random.range(self.angle.0, self.angle.1).to_radians();
+        let speed = self.random.range(self.speed.0, self.speed.1);
+        let lifetime = self.random.range(self.lifetime.0, self.lifetime.1);
+        self.particles.push(Particle {
+            pos: Vector2::new(0.0, 0.0),
+            velocity: Vector2::new(angle.cos() * speed, angle.sin() * speed),
+            age: 0.0,
+            lifetime,
+        });
+    }
+
+    pub fn get_color(&self, particle: &Particle) -> [f32; 4] {
+        let progress = particle.get_progress();
+        let mut color = [0.0; 4];
+        for i in 0..4 {
+            color[i] = self.start_color[i] + (self.end_color[i] - self.start_color[i]) * progress;
+        }
+        color
+    }
+
+    /// True for frames that haven't been looked up in the spritesheets yet
+    pub fn needs_frames_resolved(&self) -> bool {
+        self.frames.len() > 0 && self.sheet.is_none()
+    }
+
+    /**
+     * Looks the frames up once, so drawing doesn't search the spritesheets per particle.
+     * Particles are drawn in one go, so the frames all have to be on the same sheet. On an
+     * error the frames are dropped and the particles are drawn as squares.
+     */
+    pub fn resolve_frames<R: gfx::Resources>(
+        &mut self,
+        spritesheet_map: &SpritesheetMap<R>,
+    ) -> Result<(), String> {
+        match find_regions(&self.frames, spritesheet_map) {
+            Ok((sheet, regions)) => {
+                self.sheet = sheet;
+                self.regions = regions;
+                Ok(())
+            }
+            Err(err) => {
+                self.frames.clear();
+                Err(err)
+            }
+        }
+    }
+
+    /// The sheet the frames are on, None when drawn as squares or not resolved yet
+    pub fn get_sheet(&self) -> Option<&str> {
+        self.sheet.as_ref().map(|sheet| sheet.as_str())
+    }
+
+    /// Texture coordinates and size of the particle's current frame, once resolved
+    pub fn get_region(&self, particle: &Particle) -> Option<&([f32; 4], (f32, f32))> {
+        if self.regions.len() == 0 {
+            return None;
+        }
+        let index = (particle.get_progress() * self.regions.len() as f32) as usize;
+        self.regions.get(index.min(self.regions.len() - 1))
+    }
+}
+
+fn find_regions<R: gfx::Resources>(
+    frames: &[String],
+    spritesheet_map: &SpritesheetMap<R>,
+) -> Result<(Option<String>, Vec<([f32; 4], (f32, f32))>), String> {
+    let mut sheet: Option<&str> = None;
+    let mut regions = Vec::with_capacity(frames.len());
+    for frame_name in frames {
+        let (frame_sheet, uvs, size) = spritesheet_map
+            .get_frame_region(frame_name)
+            .ok_or_else(|| format!("No spritesheet has the particle frame {}", frame_name))?;
+        if let Some(sheet) = sheet {
+            if sheet != frame_sheet {
+                return Err(format!(
+                    "Particle frames should share a sheet, {} is on {} rather than {}",
+                    frame_name, frame_sheet, sheet
+                ));
+            }
+        }
+        sheet = Some(frame_sheet);
+        regions.push((uvs, size));
+    }
+
+    Ok((sheet.map(|sheet| sheet.to_string()), regions))
+}
+
+impl Component for ParticleEmitter {
+    type Storage = VecStorage<Self>;
+}
@@ -0,0 +1,62 @@
+use std::collections::HashMap;
+
+struct PassSettings {
+    enabled: bool,
+    params: Option<[f32; 4]>,
+}
+
+/**
+ * Which of the renderer's post processing passes run, by the name they were registered with.
+ * Passes always run in registration order, toggling one here doesn't change that. They only
+ * touch the world, the ui is drawn after them.
+ */
+#[derive(Default)]
+pub struct PostProcessing {
+    passes: HashMap<String, PassSettings>,
+}
+
+impl PostProcessing {
+    pub fn new() -> PostProcessing {
+        PostProcessing {
+            passes: HashMap::new(),
+        }
+    }
+
+    fn get_settings_mut(&mut self, name: &str) -> &mut PassSettings {
+        self.passes
+            .entry(name.to_string())
+            .or_insert_with(|| PassSettings {
+                enabled: false,
+                params: None,
+            })
+    }
+
+    pub fn enable(&mut self, name: &str) {
+        self.get_settings_mut(name).enabled = true;
+    }
+
+    pub fn disable(&mut self, name: &str) {
+        self.get_settings_mut(name).enabled = false;
+    }
+
+    pub fn toggle(&mut self, name: &str) {
+        let settings = self.get_settings_mut(name);
+        settings.enabled = !settings.enabled;
+    }
+
+    pub fn is_enabled(&self, name: &str) -> bool {
+        self.passes
+            .get(name)
+            .map(|settings| settings.enabled)
+            .unwrap_or(false)
+    }
+
+    /// Overrides the default uniforms of a pass, see the shader for what each one means
+    pub fn set_params(&mut self, name: &str, params: [f32; 4]) {
+        self.get_settings_mut(name).params = Some(params);
+    }
+
+    pub fn get_params(&self, name: &str) -> Option<[f32; 4]> {
+        self.passes.get(name).and_then(|settings| settings.params)
+    }
+}
@@ -9,25 +9,138 @@ use specs::{Component, VecStorage};
 
 use crate::renderer::Vertex;
 
+const DEFAULT_STROKE_WIDTH: f32 = 4.0;
+
+/**
+ * Tessellated outline or fill. Points are relative to the entity's position, circles, ellipses
+ * and arcs are centered on it, rectangles have their top left corner there.
+ */
 pub struct Shape {
     pub buffers: VertexBuffers<Vertex, u16>,
     pub points: Vec<Vector2<f32>>,
     pub color: [f32; 4],
+    pub fill: bool,
+    pub closed: bool,
+    pub stroke_width: f32,
 }
 
 impl Shape {
     pub fn new(points: Vec<Vector2<f32>>, color: [f32; 4], fill: bool) -> Self {
+        Self::from_points(points, color, fill, true)
+    }
+
+    /// Open path, always stroked
+    pub fn polyline(points: Vec<Vector2<f32>>, color: [f32; 4]) -> Self {
+        Self::from_points(points, color, false, false)
+    }
+
+    pub fn circle(radius: f32, color: [f32; 4], fill: bool) -> Self {
+        Self::ellipse(radius, radius, color, fill)
+    }
+
+    pub fn ellipse(radius_x: f32, radius_y: f32, color: [f32; 4], fill: bool) -> Self {
+        let segments = get_segments(radius_x.max(radius_y), 360.0);
+        let points = (0..segments)
+            .map(|i| {
+                let angle = (i as f32 / segments as f32 * 360.0).to_radians();
+                Vector2::new(angle.cos() * radius_x, angle.sin() * radius_y)
+            })
+            .collect();
+        Self::from_points(points, color, fill, true)
+    }
+
+    /**
+     * Angles in degrees, 0 being right and 90 down. Filled arcs are drawn as a pie slice
+     */
+    pub fn arc(radius: f32, start_angle: f32, end_angle: f32, color: [f32; 4], fill: bool) -> Self {
+        let sweep = end_angle - start_angle;
+        let segments = get_segments(radius, sweep.abs());
+        let mut points: Vec<Vector2<f32>> = (0..=segments)
+            .map(|i| {
+                let angle = (start_angle + sweep * i as f32 / segments as f32).to_radians();
+                Vector2::new(angle.cos() * radius, angle.sin() * radius)
+            })
+            .collect();
+
+        if fill {
+            points.insert(0, Vector2::new(0.0, 0.0));
+        }
+        Self::from_points(points, color, fill, fill)
+    }
+
+    pub fn rounded_rect(w: f32, h: f32, radius: f32, color: [f32; 4], fill: bool) -> Self {
+        let radius = radius.min(w / 2.0).min(h / 2.0);
+        let segments = get_segments(radius, 90.0);
+        // corner centers, with the angle each corner's arc starts at
+        let corners = [
+            (w - radius, radius, 270.0),
+            (w - radius, h - radius, 0.0),
+            (radius, h - radius, 90.0),
+            (radius, radius, 180.0),
+        ];
+
+        let mut points = Vec::with_capacity((segments + 1) * 4);
+        for (cx, cy, start_angle) in corners.iter() {
+            for i in 0..=segments {
+                let angle = (start_angle + 90.0 * i as f32 / segments as f32).to_radians();
+                points.push(Vector2::new(
+                    cx + angle.cos() * radius,
+                    cy + angle.sin() * radius,
+                ));
+            }
+        }
+        Self::from_points(points, color, fill, true)
+    }
+
+    fn from_points(points: Vec<Vector2<f32>>, color: [f32; 4], fill: bool, closed: bool) -> Self {
         Shape {
-            buffers: Self::build_buffers(points.clone(), color.clone(), fill),
+            buffers: Self::build_buffers(
+                points.clone(),
+                color.clone(),
+                fill,
+                closed,
+                DEFAULT_STROKE_WIDTH,
+            ),
             points,
             color,
+            fill,
+            closed,
+            stroke_width: DEFAULT_STROKE_WIDTH,
+        }
+    }
+
+    pub fn stroke_width(mut self, stroke_width: f32) -> Self {
+        self.set_stroke_width(stroke_width);
+        self
+    }
+
+    /// Moves every point, for shapes that shouldn't be centered on the entity
+    pub fn translate(mut self, x: f32, y: f32) -> Self {
+        for point in &mut self.points {
+            point.x += x;
+            point.y += y;
         }
+        self.set_stroke_width(self.stroke_width);
+        self
+    }
+
+    pub fn set_stroke_width(&mut self, stroke_width: f32) {
+        self.stroke_width = stroke_width;
+        self.buffers = Self::build_buffers(
+            self.points.clone(),
+            self.color.clone(),
+            self.fill,
+            self.closed,
+            self.stroke_width,
+        );
     }
 
     pub fn build_buffers(
         points: Vec<Vector2<f32>>,
         color: [f32; 4],
         fill: bool,
+        closed: bool,
+        stroke_width: f32,
     ) -> VertexBuffers<Vertex, u16> {
         let mut path_builder = Path::builder();
         for (i, point) in points.iter().enumerate() {
@@ -39,7 +152,9 @@ impl Shape {
             }
         }
 
-        path_builder.close();
+        if closed {
+            path_builder.close();
+        }
 
         let path = path_builder.build();
         let mut buffers = VertexBuffers::new();
@@ -61,7 +176,7 @@ impl Shape {
             // Compute the tessellation.
             tessellator.tessellate_path(
                 path.iter(),
-                &StrokeOptions::default().with_line_width(4.0),
+                &StrokeOptions::default().with_line_width(stroke_width),
                 &mut BuffersBuilder::new(&mut buffers, VertexCtor { color }),
             );
         }
@@ -70,12 +185,21 @@ impl Shape {
     }
 
     pub fn set_color(&mut self, color: [f32; 4]) {
+        self.color = color;
         for vertex in &mut self.buffers.vertices {
             vertex.color = color.clone();
         }
     }
 }
 
+/**
+ * Enough line segments for a curve to look round at its size, without going overboard
+ */
+fn get_segments(radius: f32, degrees: f32) -> usize {
+    let full_circle = (radius * 0.5).max(12.0).min(64.0);
+    ((full_circle * degrees / 360.0).ceil() as usize).max(2)
+}
+
 struct VertexCtor {
     pub color: [f32; 4],
 }
@@ -1,13 +1,75 @@
 use cgmath::Vector2;
-use gfx_glyph::HorizontalAlign;
+use gfx_glyph::{HorizontalAlign, VerticalAlign};
 use rusttype::Scale;
 use specs::{Component, VecStorage};
 
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TextWrap {
+    /// Breaks lines between words when they reach the text width
+    Word,
+    /// Breaks lines at any character, for text without spaces
+    AnyChar,
+    /// Keeps everything on one line
+    None,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TextSpace {
+    /// Placed in the scene, moves with the camera. For damage numbers, names over NPCs
+    World,
+    /// Fixed on screen regardless of the camera, for UI
+    Screen,
+}
+
+/**
+ * A run of text with its own styling. Anything not set falls back to the Text's
+ * font and scale, and the entity's Color.
+ */
+pub struct TextSpan {
+    pub text: String,
+    pub color: Option<[f32; 4]>,
+    pub font: Option<String>,
+    pub scale: Option<f32>,
+}
+
+impl TextSpan {
+    pub fn new(text: &str) -> Self {
+        TextSpan {
+            text: text.to_string(),
+            color: None,
+            font: None,
+            scale: None,
+        }
+    }
+
+    pub fn color(mut self, color: [f32; 4]) -> Self {
+        self.color = Some(color);
+        self
+    }
+
+    pub fn font(mut self, font: &str) -> Self {
+        self.font = Some(font.to_string());
+        self
+    }
+
+    pub fn scale(mut self, scale: f32) -> Self {
+        self.scale = Some(scale);
+        self
+    }
+}
+
 pub struct Text {
     pub align: HorizontalAlign,
+    pub v_align: VerticalAlign,
+    pub wrap: TextWrap,
+    pub space: TextSpace,
+    /// Name from the FontMap, uses the first font when not set
+    pub font: Option<String>,
     pub scale: Scale,
     pub new_data: bool,
     pub text: String,
+    /// Drawn instead of text when not empty
+    pub spans: Vec<TextSpan>,
     pub visible: bool,
     pub size: Vector2<u16>,
 }
@@ -18,9 +80,14 @@ impl Text {
 
         Text {
             align: HorizontalAlign::Left,
+            v_align: VerticalAlign::Top,
+            wrap: TextWrap::Word,
+            space: TextSpace::Screen,
+            font: None,
             scale: scale,
             new_data: false,
             text: "".to_string(),
+            spans: Vec::new(),
             visible: true,
             size: Vector2 { x: w, y: h },
         }
@@ -32,10 +99,45 @@ impl Text {
         text_component
     }
 
+    pub fn new_with_spans(size: f32, w: u16, h: u16, spans: Vec<TextSpan>) -> Text {
+        let mut text_component = Text::new(size, w, h);
+        text_component.spans = spans;
+        text_component
+    }
+
     pub fn align(mut self, align: HorizontalAlign) -> Self {
         self.align = align;
         self
     }
+
+    pub fn v_align(mut self, v_align: VerticalAlign) -> Self {
+        self.v_align = v_align;
+        self
+    }
+
+    pub fn wrap(mut self, wrap: TextWrap) -> Self {
+        self.wrap = wrap;
+        self
+    }
+
+    pub fn font(mut self, font: &str) -> Self {
+        self.font = Some(font.to_string());
+        self
+    }
+
+    pub fn space(mut self, space: TextSpace) -> Self {
+        self.space = space;
+        self
+    }
+
+    pub fn set_spans(&mut self, spans: Vec<TextSpan>) {
+        self.spans = spans;
+        self.new_data = true;
+    }
+
+    pub fn has_content(&self) -> bool {
+        self.text != "" || self.spans.iter().any(|span| span.text != "")
+    }
 }
 
 impl Component for Text {
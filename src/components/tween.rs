@@ -0,0 +1,387 @@
+use std::f32::consts::PI;
+
+use cgmath::Vector2;
+use specs::{Component, Entity, VecStorage};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Easing {
+    Linear,
+    QuadIn,
+    QuadOut,
+    QuadInOut,
+    CubicIn,
+    CubicOut,
+    CubicInOut,
+    ElasticIn,
+    ElasticOut,
+    BackIn,
+    BackOut,
+}
+
+impl Easing {
+    /// Maps progress from 0 to 1 onto the curve. Elastic and back overshoot past 0 and 1
+    pub fn apply(&self, t: f32) -> f32 {
+        // how far back overshoots, about 10%
+        const BACK: f32 = 1.70158;
+        const ELASTIC: f32 = 2.0 * PI / 3.0;
+
+        match self {
+            Easing::Linear => t,
+            Easing::QuadIn => t * t,
+            Easing::QuadOut => t * (2.0 - t),
+            Easing::QuadInOut => {
+                if t < 0.5 {
+                    2.0 * t * t
+                } else {
+                    -1.0 + (4.0 - 2.0 * t) * t
+                }
+            }
+            Easing::CubicIn => t * t * t,
+            Easing::CubicOut => (t - 1.0).powi(3) + 1.0,
+            Easing::CubicInOut => {
+                if t < 0.5 {
+                    4.0 * t * t * t
+                } else {
+                    (t - 1.0) * (2.0 * t - 2.0).powi(2) + 1.0
+                }
+            }
+            Easing::ElasticIn => {
+                if t <= 0.0 || t >= 1.0 {
+                    t
+                } else {
+                    -(2.0f32.powf(10.0 * t - 10.0)) * ((t * 10.0 - 10.75) * ELASTIC).sin()
+                }
+            }
+            Easing::ElasticOut => {
+                if t <= 0.0 || t >= 1.0 {
+                    t
+                } else {
+                    2.0f32.powf(-10.0 * t) * ((t * 10.0 - 0.75) * ELASTIC).sin() + 1.0
+                }
+            }
+            Easing::BackIn => (BACK + 1.0) * t * t * t - BACK * t * t,
+            Easing::BackOut => 1.0 + (BACK + 1.0) * (t - 1.0).powi(3) + BACK * (t - 1.0).powi(2),
+        }
+    }
+}
+
+/**
+ * What a tween animates, and between which values
+ */
+#[derive(Clone, Debug, PartialEq)]
+pub enum TweenProperty {
+    /// Transform position, relative to the parent like set_pos2
+    Position(Vector2<f32>, Vector2<f32>),
+    /// Transform size, rounded to whole pixels
+    Size(Vector2<f32>, Vector2<f32>),
+    /// Color alpha
+    Alpha(f32, f32),
+    /// Color RGB, leaving the alpha alone
+    Rgb([f32; 3], [f32; 3]),
+    TextScale(f32, f32),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Repeat {
+    /// Plays this many more times after the first
+    Times(u32),
+    Forever,
+}
+
+/**
+ * Animates one property of the entity from a start to an end value, see TweenSystem.
+ * The component is removed once it finishes, and a TweenEvent is sent.
+ */
+pub struct Tween {
+    pub property: TweenProperty,
+    pub duration: f32,
+    pub easing: Easing,
+    /// Seconds to wait before the first play
+    pub delay: f32,
+    pub repeat: Repeat,
+    /// Plays every other repeat backwards
+    pub yoyo: bool,
+    /// Sent with the TweenEvent, so listeners can tell tweens apart
+    pub name: String,
+    time_passed: f32,
+    iteration: u32,
+    finished: bool,
+}
+
+impl Tween {
+    pub fn new(property: TweenProperty, duration: f32) -> Self {
+        Tween {
+            property,
+            duration,
+            easing: Easing::Linear,
+            delay: 0.0,
+            repeat: Repeat::Times(0),
+            yoyo: false,
+            name: String::new(),
+            time_passed: 0.0,
+            iteration: 0,
+            finished: false,
+        }
+    }
+
+    pub fn fade(from: f32, to: f32, duration: f32) -> Self {
+        Tween::new(TweenProperty::Alpha(from, to), duration)
+    }
+
+    pub fn move_between(from: Vector2<f32>, to: Vector2<f32>, duration: f32) -> Self {
+        Tween::new(TweenProperty::Position(from, to), duration)
+    }
+
+    pub fn easing(mut self, easing: Easing) -> Self {
+        self.easing = easing;
+        self
+    }
+
+    pub fn delay(mut self, delay: f32) -> Self {
+        self.delay = delay;
+        self
+    }
+
+    pub fn repeat(mut self, repeat: Repeat) -> Self {
+        self.repeat = repeat;
+        self
+    }
+
+    pub fn yoyo(mut self) -> Self {
+        self.yoyo = true;
+        self
+    }
+
+    pub fn name(mut self, name: &str) -> Self {
+        self.name = name.to_string();
+        self
+    }
+
+    pub fn update(&mut self, dt: f32) {
+        if self.finished {
+            return;
+        }
+
+        let mut dt = dt;
+        if self.delay > 0.0 {
+            self.delay -= dt;
+            if self.delay > 0.0 {
+                return;
+            }
+            // carry over whatever went past the delay
+            dt = -self.delay;
+            self.delay = 0.0;
+        }
+
+        self.time_passed += dt;
+        // a zero length tween would never get through a repeat
+        let duration = self.duration.max(0.0001);
+        while self.time_passed >= duration {
+            if let Repeat::Times(times) = self.repeat {
+                if self.iteration >= times {
+                    self.time_passed = duration;
+                    self.finished = true;
+                    return;
+                }
+            }
+            self.time_passed -= duration;
+            self.iteration += 1;
+        }
+    }
+
+    /// False while waiting on the delay, the property is left alone until then
+    pub fn is_started(&self) -> bool {
+        self.delay <= 0.0
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.finished
+    }
+
+    /// Eased progress through the current play, 0 being the start value and 1 the end
+    pub fn get_progress(&self) -> f32 {
+        let mut t = (self.time_passed / self.duration.max(0.0001)).min(1.0);
+        if self.yoyo && self.iteration % 2 == 1 {
+            t = 1.0 - t;
+        }
+        self.easing.apply(t)
+    }
+}
+
+impl Component for Tween {
+    type Storage = VecStorage<Self>;
+}
+
+pub struct TweenEvent {
+    pub entity: Entity,
+    pub name: String,
+}
+
+/**
+ * Tweens that finished during the last TweenSystem run
+ */
+#[derive(Default)]
+pub struct TweenEvents {
+    pub completed: Vec<TweenEvent>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EASINGS: [Easing; 11] = [
+        Easing::Linear,
+        Easing::QuadIn,
+        Easing::QuadOut,
+        Easing::QuadInOut,
+        Easing::CubicIn,
+        Easing::CubicOut,
+        Easing::CubicInOut,
+        Easing::ElasticIn,
+        Easing::ElasticOut,
+        Easing::BackIn,
+        Easing::BackOut,
+    ];
+
+    fn assert_near(value: f32, expected: f32) {
+        assert!(
+            (value - expected).abs() < 0.00001,
+            "{} is not {}",
+            value,
+            expected
+        );
+    }
+
+    fn tween() -> Tween {
+        Tween::fade(0.0, 1.0, 1.0)
+    }
+
+    #[test]
+    fn easings_start_at_zero_and_end_at_one() {
+        for easing in EASINGS.iter() {
+            assert_near(easing.apply(0.0), 0.0);
+            assert_near(easing.apply(1.0), 1.0);
+        }
+    }
+
+    #[test]
+    fn in_out_easings_are_halfway_at_the_middle() {
+        assert_near(Easing::Linear.apply(0.5), 0.5);
+        assert_near(Easing::QuadInOut.apply(0.5), 0.5);
+        assert_near(Easing::CubicInOut.apply(0.5), 0.5);
+    }
+
+    #[test]
+    fn back_overshoots() {
+        assert!(Easing::BackIn.apply(0.2) < 0.0);
+        assert!(Easing::BackOut.apply(0.8) > 1.0);
+    }
+
+    #[test]
+    fn plays_once_and_finishes_on_the_end_value() {
+        let mut tween = tween();
+
+        tween.update(0.5);
+        assert_near(tween.get_progress(), 0.5);
+        assert!(!tween.is_finished());
+
+        tween.update(0.75);
+        assert!(tween.is_finished());
+        assert_near(tween.get_progress(), 1.0);
+
+        tween.update(0.5);
+        assert_near(tween.get_progress(), 1.0);
+    }
+
+    #[test]
+    fn progress_is_eased() {
+        let mut tween = tween().easing(Easing::QuadIn);
+
+        tween.update(0.5);
+        assert_near(tween.get_progress(), 0.25);
+    }
+
+    #[test]
+    fn delay_holds_then_carries_over() {
+        let mut tween = tween().delay(0.5);
+
+        tween.update(0.25);
+        assert!(!tween.is_started());
+        assert_near(tween.get_progress(), 0.0);
+
+        tween.update(0.5);
+        assert!(tween.is_started());
+        assert_near(tween.get_progress(), 0.25);
+    }
+
+    #[test]
+    fn repeats_restart_from_the_start_value() {
+        let mut tween = tween().repeat(Repeat::Times(2));
+
+        tween.update(1.25);
+        assert!(!tween.is_finished());
+        assert_near(tween.get_progress(), 0.25);
+
+        tween.update(1.0);
+        assert_near(tween.get_progress(), 0.25);
+
+        tween.update(0.75);
+        assert!(tween.is_finished());
+        assert_near(tween.get_progress(), 1.0);
+    }
+
+    #[test]
+    fn yoyo_plays_every_other_repeat_backwards() {
+        let mut tween = tween().repeat(Repeat::Times(2)).yoyo();
+
+        tween.update(0.25);
+        assert_near(tween.get_progress(), 0.25);
+        tween.update(1.0);
+        assert_near(tween.get_progress(), 0.75);
+        tween.update(1.0);
+        assert_near(tween.get_progress(), 0.25);
+
+        // an even number of repeats comes back out on the end value
+        tween.update(0.75);
+        assert!(tween.is_finished());
+        assert_near(tween.get_progress(), 1.0);
+    }
+
+    #[test]
+    fn yoyo_with_odd_repeats_finishes_on_the_start_value() {
+        let mut tween = tween().repeat(Repeat::Times(1)).yoyo();
+
+        tween.update(1.0);
+        assert!(!tween.is_finished());
+        assert_near(tween.get_progress(), 1.0);
+
+        tween.update(0.25);
+        assert_near(tween.get_progress(), 0.75);
+
+        tween.update(0.75);
+        assert!(tween.is_finished());
+        assert_near(tween.get_progress(), 0.0);
+    }
+
+    #[test]
+    fn forever_never_finishes() {
+        let mut tween = tween().repeat(Repeat::Forever).yoyo();
+
+        tween.update(10.5);
+        assert!(!tween.is_finished());
+        assert_near(tween.get_progress(), 0.5);
+
+        tween.update(1.25);
+        assert!(!tween.is_finished());
+        assert_near(tween.get_progress(), 0.25);
+    }
+
+    #[test]
+    fn zero_duration_finishes_straight_away() {
+        let mut tween = Tween::fade(0.0, 1.0, 0.0).repeat(Repeat::Times(3));
+
+        tween.update(0.016);
+        assert!(tween.is_finished());
+        assert_near(tween.get_progress(), 1.0);
+    }
+}
@@ -0,0 +1,25 @@
+use specs::{Component, VecStorage};
+
+/**
+ * Area that starts a MapTransition when the player walks into it, see WarpSystem
+ */
+pub struct Warp {
+    pub map: String,
+    pub spawn: String,
+    /// Only set once the player is outside, so arriving on top of a warp doesn't take it
+    pub armed: bool,
+}
+
+impl Warp {
+    pub fn new(map: &str, spawn: &str) -> Self {
+        Warp {
+            map: map.to_string(),
+            spawn: spawn.to_string(),
+            armed: false,
+        }
+    }
+}
+
+impl Component for Warp {
+    type Storage = VecStorage<Self>;
+}
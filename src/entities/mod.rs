@@ -1,75 +1,432 @@
+use std::any::Any;
+
+use cgmath::Vector2;
 use specs::{world::Builder, Entity, World};
-use tiled::{Group, Map, ObjectGroup, ObjectShape, PropertyValue};
+use tiled::{Group, ImageLayer, Layer, Map, ObjectGroup, ObjectShape, Properties, PropertyValue};
 
-use crate::components::{AnimationSheet, Node, Player, Sprite, Transform};
+use crate::{
+    assets::{animation_library::AnimationLibrary, prefabs::PrefabRegistry},
+    components::{
+        tiled::{color_from_argb, TiledMap},
+        Animation, AnimationController, AnimationSheet, Collider, ColliderShape, EntityLookup,
+        Facing, FacingMode, Light, LoopMode, Node, Parallax, Player, Shape, SortMode, Sprite,
+        Transform, Warp,
+    },
+};
 
-fn add_group_to_world(
+/**
+ * Children of a map or group, in the order Tiled draws them
+ */
+enum MapLayer<'a> {
+    Tiles(&'a Layer),
+    Objects(&'a ObjectGroup),
+    Image(&'a ImageLayer),
+    Group(&'a Group),
+}
+
+impl<'a> MapLayer<'a> {
+    fn from_any(layer: &'a Box<dyn Any>) -> Option<MapLayer<'a>> {
+        if let Some(layer) = layer.downcast_ref::<Layer>() {
+            Some(MapLayer::Tiles(layer))
+        } else if let Some(object_group) = layer.downcast_ref::<ObjectGroup>() {
+            Some(MapLayer::Objects(object_group))
+        } else if let Some(image_layer) = layer.downcast_ref::<ImageLayer>() {
+            Some(MapLayer::Image(image_layer))
+        } else if let Some(group) = layer.downcast_ref::<Group>() {
+            Some(MapLayer::Group(group))
+        } else {
+            None
+        }
+    }
+
+    /**
+     * The parallax_x and parallax_y number properties, 1 when not set. The tiled crate doesn't
+     * read Tiled's own parallaxx and parallaxy attributes, so maps use these instead
+     */
+    fn get_parallax(&self) -> (f32, f32) {
+        let properties = match self {
+            MapLayer::Tiles(layer) => &layer.properties,
+            MapLayer::Objects(group) => &group.properties,
+            MapLayer::Image(layer) => &layer.properties,
+            MapLayer::Group(group) => &group.properties,
+        };
+
+        (
+            get_f32(properties, "parallax_x").unwrap_or(1.0),
+            get_f32(properties, "parallax_y").unwrap_or(1.0),
+        )
+    }
+}
+
+/**
+ * A number property as a float. Whole numbers come through as ints, from Tiled's int properties
+ * or prefabs written like 48, so those count too
+ */
+fn get_f32(properties: &Properties, name: &str) -> Option<f32> {
+    match properties.get(name) {
+        Some(PropertyValue::FloatValue(value)) => Some(*value),
+        Some(PropertyValue::IntValue(value)) => Some(*value as f32),
+        _ => None,
+    }
+}
+
+/**
+ * A light for objects with a light_radius, coloured by light_color. light_intensity and
+ * light_flicker default to 1 and 0
+ */
+fn light_from_properties(properties: &Properties) -> Option<Light> {
+    let radius = get_f32(properties, "light_radius")?;
+    let color = match properties.get("light_color") {
+        Some(PropertyValue::ColorValue(argb)) => {
+            let color = color_from_argb(*argb);
+            [color[0], color[1], color[2]]
+        }
+        _ => [1.0; 3],
+    };
+    let intensity = get_f32(properties, "light_intensity").unwrap_or(1.0);
+    let flicker = get_f32(properties, "light_flicker").unwrap_or(0.0);
+
+    Some(Light::new(color, radius, intensity).flicker(flicker, 8.0))
+}
+
+/**
+ * Builds an animation from a comma separated frame list, where a frame can set its own
+ * duration in seconds as frame:seconds. The loop mode comes from the loop_<name> property,
+ * and tagged frames from tags_<name> as a list of index:tag.
+ */
+fn animation_from_properties(name: &str, frames: &str, properties: &Properties) -> Animation {
+    let mut frame_names = Vec::new();
+    let mut frame_durations = Vec::new();
+    for (i, frame) in frames.split(",").enumerate() {
+        let mut parts = frame.trim().split(":");
+        frame_names.push(format!("{}.png", parts.next().unwrap()));
+        if let Some(duration) = parts.next() {
+            let duration = duration
+                .parse::<f32>()
+                .unwrap_or_else(|_| panic!("Bad duration for frame {} of {}", i, name));
+            frame_durations.push((i, duration));
+        }
+    }
+
+    let mut animation = Animation::new(frame_names);
+    for (i, duration) in frame_durations {
+        animation = animation.frame_duration(i, duration);
+    }
+
+    if let Some(PropertyValue::StringValue(loop_mode)) = properties.get(&format!("loop_{}", name)) {
+        let loop_mode = LoopMode::from_name(loop_mode)
+            .unwrap_or_else(|| panic!("Unknown loop mode {} for {}", loop_mode, name));
+        animation = animation.loop_mode(loop_mode);
+    }
+
+    if let Some(PropertyValue::StringValue(tags)) = properties.get(&format!("tags_{}", name)) {
+        for tag in tags.split(",") {
+            let mut parts = tag.trim().split(":");
+            let index = parts
+                .next()
+                .and_then(|index| index.parse::<usize>().ok())
+                .unwrap_or_else(|| panic!("Bad tag {} for {}", tag, name));
+            animation = animation.tag(index, parts.next().unwrap_or(""));
+        }
+    }
+
+    animation
+}
+
+fn add_layer_to_world(
     world: &mut World,
-    parent: Option<Entity>,
-    group: &Group,
+    parent: Entity,
+    map: &Map,
+    layer: &Layer,
+    depth: f32,
+) -> Entity {
+    let entity = world
+        .create_entity()
+        .with(TiledMap::new(map, layer))
+        .with(Node::with_parent(parent))
+        .with(Transform::new(
+            layer.offset_x,
+            layer.offset_y,
+            depth,
+            (map.width * map.tile_width) as u16,
+            (map.height * map.tile_height) as u16,
+            layer.visible,
+        ))
+        .build();
+
+    let mut lookup = world.write_resource::<EntityLookup>();
+    lookup.insert(&format!("layer_{}", layer.name), entity);
+
+    entity
+}
+
+fn add_image_layer_to_world(
+    world: &mut World,
+    parent: Entity,
+    layer: &ImageLayer,
+    depth: f32,
+) -> Entity {
+    let (w, h) = match &layer.image {
+        Some(image) => (image.width as u16, image.height as u16),
+        None => (1, 1),
+    };
+
+    let mut builder = world
+        .create_entity()
+        .with(Node::with_parent(parent))
+        .with(Transform::new(
+            layer.offset_x,
+            layer.offset_y,
+            depth,
+            w,
+            h,
+            layer.visible,
+        ));
+    if let Some(tiled_map) = TiledMap::from_image_layer(layer) {
+        builder = builder.with(tiled_map);
+    }
+    let entity = builder.build();
+
+    let mut lookup = world.write_resource::<EntityLookup>();
+    lookup.insert(&format!("layer_{}", layer.name), entity);
+
+    entity
+}
+
+fn points_from_object(points: &Vec<(f32, f32)>) -> Vec<Vector2<f32>> {
+    points.iter().map(|(x, y)| Vector2::new(*x, *y)).collect()
+}
+
+/**
+ * Polygons, polylines and ellipses collide unless the collider property turns it off,
+ * rectangles only when it's turned on. Ellipses become circles with the average of their
+ * radii, centered in the object. The sensor property makes an area that doesn't block.
+ */
+fn collider_from_object(shape: &ObjectShape, properties: &Properties) -> Option<Collider> {
+    let enabled = match properties.get("collider") {
+        Some(PropertyValue::BoolValue(enabled)) => *enabled,
+        _ => match shape {
+            ObjectShape::Rect { .. } | ObjectShape::Point(..) => false,
+            _ => true,
+        },
+    };
+    if !enabled {
+        return None;
+    }
+
+    let collider = match shape {
+        ObjectShape::Rect { width, height } => Collider::new(ColliderShape::Rect {
+            w: *width,
+            h: *height,
+        }),
+        ObjectShape::Ellipse { width, height } => Collider::new(ColliderShape::Circle {
+            radius: (width + height) / 4.0,
+        })
+        .offset(width / 2.0, height / 2.0),
+        ObjectShape::Polygon { points } => {
+            Collider::new(ColliderShape::Polygon(points_from_object(points)))
+        }
+        ObjectShape::Polyline { points } => {
+            Collider::new(ColliderShape::Polyline(points_from_object(points)))
+        }
+        ObjectShape::Point(..) => return None,
+    };
+
+    let sensor = properties.get("sensor") == Some(&PropertyValue::BoolValue(true));
+    Some(collider.sensor(sensor))
+}
+
+/**
+ * Objects with a shape_color property are drawn as that shape, filled when shape_fill is set.
+ * Polylines are always stroked, for paths.
+ */
+fn shape_from_object(shape: &ObjectShape, properties: &Properties) -> Option<Shape> {
+    let color = match properties.get("shape_color") {
+        Some(PropertyValue::ColorValue(argb)) => color_from_argb(*argb),
+        _ => return None,
+    };
+    let fill = properties.get("shape_fill") == Some(&PropertyValue::BoolValue(true));
+
+    match shape {
+        ObjectShape::Rect { width, height } => Some(Shape::new(
+            vec![
+                Vector2::new(0.0, 0.0),
+                Vector2::new(*width, 0.0),
+                Vector2::new(*width, *height),
+                Vector2::new(0.0, *height),
+            ],
+            color,
+            fill,
+        )),
+        ObjectShape::Ellipse { width, height } => Some(
+            Shape::ellipse(width / 2.0, height / 2.0, color, fill)
+                .translate(width / 2.0, height / 2.0),
+        ),
+        ObjectShape::Polygon { points } => {
+            Some(Shape::new(points_from_object(points), color, fill))
+        }
+        ObjectShape::Polyline { points } => {
+            Some(Shape::polyline(points_from_object(points), color))
+        }
+        ObjectShape::Point(..) => None,
+    }
+}
+
+fn add_object_group_to_world(
+    world: &mut World,
+    parent: Entity,
+    map: &Map,
+    object_group: &ObjectGroup,
     depth: f32,
 ) -> Entity {
-    let mut group_node = Node::new();
-    group_node.parent = parent;
     // width/height here doesnt matter, not using view clipping
-    let transform = Transform::visible(group.offset_x, group.offset_y, depth, 1, 1);
+    let transform = Transform::new(0.0, 0.0, depth, 1, 1, object_group.visible);
+
+    let sort_mode =
+        if object_group.properties.get("y_sort") == Some(&PropertyValue::BoolValue(true)) {
+            SortMode::BottomEdge
+        } else {
+            SortMode::Depth
+        };
 
     let entity = world
         .create_entity()
-        .with(group_node)
+        .with(Node::with_parent(parent).sort_mode(sort_mode))
         .with(transform)
         .build();
 
     let mut children = Vec::new();
 
-    for child_layer in &group.children {
-        if let Some(object_group) = child_layer.downcast_ref::<ObjectGroup>() {
-            for object in &object_group.objects {
-                let transform = match object.shape {
-                    ObjectShape::Rect { width, height } => {
-                        Transform::visible(object.x, object.y, depth, width as u16, height as u16)
-                    }
-                    _ => Transform::visible(object.x, object.y, depth, 1, 1),
-                };
-
-                let mut builder = world.create_entity().with(transform);
-
-                if object.properties.contains_key("frame") {
-                    let frame_name = object.properties.get("frame").unwrap();
-                    if let PropertyValue::StringValue(frame_name) = frame_name {
-                        builder = builder.with(Sprite::new(&frame_name));
-                    }
-                }
+    for object in &object_group.objects {
+        let properties = world
+            .read_resource::<PrefabRegistry>()
+            .get_properties(&object.properties);
+
+        // animations from an Aseprite export, animation_ properties can add to or replace them
+        let library_animations = match properties.get("animations") {
+            Some(PropertyValue::StringValue(name)) => {
+                Some(world.read_resource::<AnimationLibrary>().build_sheet(name))
+            }
+            _ => None,
+        };
+
+        // objects share the depth of their group, draw order within it comes from the node
+        let transform = match object.shape {
+            // tile objects are positioned by their bottom left corner
+            ObjectShape::Rect { width, height } if object.gid != 0 => Transform::visible(
+                object.x,
+                object.y - height,
+                0.0,
+                width as u16,
+                height as u16,
+            ),
+            ObjectShape::Rect { width, height } | ObjectShape::Ellipse { width, height } => {
+                Transform::visible(object.x, object.y, 0.0, width as u16, height as u16)
+            }
+            _ => Transform::visible(object.x, object.y, 0.0, 1, 1),
+        };
+
+        let mut builder = world
+            .create_entity()
+            .with(transform)
+            .with(Node::with_parent(entity));
+
+        if properties.contains_key("frame") {
+            let frame_name = properties.get("frame").unwrap();
+            if let PropertyValue::StringValue(frame_name) = frame_name {
+                builder = builder.with(Sprite::new(&frame_name));
+            }
+        }
 
-                if object.properties.get("type")
-                    == Some(&PropertyValue::StringValue("player".to_string()))
-                {
-                    builder = builder.with(Player::new());
+        if object.gid != 0 {
+            if let ObjectShape::Rect { width, height } = object.shape {
+                match TiledMap::from_tile_object(map, &object.name, object.gid, width, height) {
+                    Some(tiled_map) => builder = builder.with(tiled_map),
+                    // like tiles in a layer, ones missing from the tilesets aren't drawn
+                    None => eprintln!(
+                        "No tileset has tile {} for object {}, leaving it out",
+                        object.gid, object.name
+                    ),
                 }
+            }
+        }
+
+        if let Some(collider) = collider_from_object(&object.shape, &properties) {
+            builder = builder.with(collider);
+        }
+
+        if let Some(shape) = shape_from_object(&object.shape, &properties) {
+            builder = builder.with(shape);
+        }
+
+        if properties.get("player") == Some(&PropertyValue::BoolValue(true)) {
+            builder = builder.with(Player::new());
+        }
 
-                let mut animation = AnimationSheet::new(0.1);
-                for (key, value) in &object.properties {
-                    if key.starts_with("animation_") {
-                        if let PropertyValue::StringValue(frames) = value {
-                            let frames: Vec<String> = frames
-                                .split(",")
-                                .map(|frame| format!("{}.png", frame))
-                                .collect();
-                            animation
-                                .add_animation(key.replace("animation_", "").to_string(), frames);
-                        }
-                    }
+        if let Some(PropertyValue::StringValue(map_name)) = properties.get("warp_map") {
+            match properties.get("warp_spawn") {
+                Some(PropertyValue::StringValue(spawn)) => {
+                    builder = builder.with(Warp::new(map_name, spawn));
                 }
+                _ => eprintln!(
+                    "Warp {} has no warp_spawn object name, leaving the warp out",
+                    object.name
+                ),
+            }
+        }
 
-                if animation.animations.len() > 0 {
-                    builder = builder.with(animation);
+        if let Some(light) = light_from_properties(&properties) {
+            builder = builder.with(light);
+        }
+
+        let frame_length = get_f32(&properties, "frame_length").unwrap_or(0.1);
+        let mut animation = library_animations.unwrap_or_else(|| AnimationSheet::new(frame_length));
+        animation.frame_length = frame_length;
+        for (key, value) in &properties {
+            if key.starts_with("animation_") {
+                if let PropertyValue::StringValue(frames) = value {
+                    let name = &key["animation_".len()..];
+                    animation.add(
+                        name.to_string(),
+                        animation_from_properties(name, frames, &properties),
+                    );
                 }
+            }
+        }
+
+        if animation.animations.len() > 0 {
+            builder = builder.with(animation);
+        }
+
+        if let Some(PropertyValue::StringValue(name)) = properties.get("controller") {
+            builder = builder.with(AnimationController::from_file(name));
+        }
 
-                children.push(builder.build());
+        match properties.get("facing") {
+            Some(PropertyValue::IntValue(4)) => {
+                builder = builder.with(Facing::new(FacingMode::Four))
+            }
+            Some(PropertyValue::IntValue(8)) => {
+                builder = builder.with(Facing::new(FacingMode::Eight))
+            }
+            Some(value) => {
+                // a typo in the map shouldn't take the game down, four way still animates
+                eprintln!(
+                    "Facing on object {} should be 4 or 8, got {:?}. Using 4",
+                    object.name, value
+                );
+                builder = builder.with(Facing::new(FacingMode::Four))
             }
-        } else if let Some(group) = child_layer.downcast_ref::<Group>() {
-            children.push(add_group_to_world(world, Some(entity), group, depth + 1.0));
+            None => {}
         }
+
+        let object_entity = builder.build();
+        // named objects can be found as spawn points, see Play::change_map
+        if object.name != "" {
+            let mut lookup = world.write_resource::<EntityLookup>();
+            lookup.insert(&format!("object_{}", object.name), object_entity);
+        }
+        children.push(object_entity);
     }
 
     let mut nodes = world.write_storage::<Node>();
@@ -79,9 +436,197 @@ fn add_group_to_world(
     entity
 }
 
-pub fn build_from_map(world: &mut World, map: &Map) -> Vec<Entity> {
-    map.groups
+fn add_group_to_world(
+    world: &mut World,
+    parent: Entity,
+    map: &Map,
+    group: &Group,
+    depth: f32,
+) -> Entity {
+    // width/height here doesnt matter, not using view clipping
+    let transform = Transform::visible(group.offset_x, group.offset_y, depth, 1, 1);
+
+    let entity = world
+        .create_entity()
+        .with(Node::with_parent(parent))
+        .with(transform)
+        .build();
+
+    // children keep the group's depth, and are drawn in the order they appear in the group
+    let children = group
+        .children
         .iter()
-        .map(|group| add_group_to_world(world, None, group, 1.0))
+        .filter_map(MapLayer::from_any)
+        .map(|layer| add_map_layer_to_world(world, entity, map, &layer, 0.0))
+        .collect();
+
+    let mut nodes = world.write_storage::<Node>();
+    let group_node = nodes.get_mut(entity).unwrap();
+    group_node.add_many(children);
+
+    entity
+}
+
+fn add_map_layer_to_world(
+    world: &mut World,
+    parent: Entity,
+    map: &Map,
+    layer: &MapLayer,
+    depth: f32,
+) -> Entity {
+    let entity = match layer {
+        MapLayer::Tiles(layer) => add_layer_to_world(world, parent, map, layer, depth),
+        MapLayer::Objects(object_group) => {
+            add_object_group_to_world(world, parent, map, object_group, depth)
+        }
+        MapLayer::Image(image_layer) => add_image_layer_to_world(world, parent, image_layer, depth),
+        MapLayer::Group(group) => add_group_to_world(world, parent, map, group, depth),
+    };
+
+    let (parallax_x, parallax_y) = layer.get_parallax();
+    if parallax_x != 1.0 || parallax_y != 1.0 {
+        let origin = *world
+            .read_storage::<Transform>()
+            .get(entity)
+            .unwrap()
+            .get_pos();
+        world
+            .write_storage::<Parallax>()
+            .insert(
+                entity,
+                Parallax::new(parallax_x, parallax_y, origin.x, origin.y),
+            )
+            .unwrap();
+    }
+
+    entity
+}
+
+fn add_group_image_sources(group: &Group, sources: &mut Vec<String>) {
+    for layer in group.children.iter().filter_map(MapLayer::from_any) {
+        match layer {
+            MapLayer::Image(ImageLayer {
+                image: Some(image), ..
+            }) => sources.push(image.source.clone()),
+            MapLayer::Group(group) => add_group_image_sources(group, sources),
+            _ => {}
+        }
+    }
+}
+
+/**
+ * Every tileset and image layer picture the map draws with, relative to the map's folder
+ */
+pub fn get_image_sources(map: &Map) -> Vec<String> {
+    let mut sources = Vec::new();
+    for tileset in &map.tilesets {
+        sources.extend(tileset.images.iter().map(|image| image.source.clone()));
+    }
+    for image_layer in &map.image_layers {
+        if let Some(image) = &image_layer.image {
+            sources.push(image.source.clone());
+        }
+    }
+    for group in &map.groups {
+        add_group_image_sources(group, &mut sources);
+    }
+
+    sources
+}
+
+fn group_has_object(group: &Group, name: &str) -> bool {
+    group
+        .children
+        .iter()
+        .filter_map(MapLayer::from_any)
+        .any(|layer| match layer {
+            MapLayer::Objects(object_group) => object_group
+                .objects
+                .iter()
+                .any(|object| object.name == name),
+            MapLayer::Group(group) => group_has_object(group, name),
+            _ => false,
+        })
+}
+
+/**
+ * Whether any object group of the map has an object with the name, like a spawn point
+ */
+pub fn has_object(map: &Map, name: &str) -> bool {
+    map.object_groups.iter().any(|object_group| {
+        object_group
+            .objects
+            .iter()
+            .any(|object| object.name == name)
+    }) || map.groups.iter().any(|group| group_has_object(group, name))
+}
+
+/**
+ * Creates a node for each top level layer, object group, image layer and group of the map.
+ * Each one gets a depth from its position in the Tiled layer list, so objects can sit between
+ * tile layers. Layers with a parallax factor other than 1 get a Parallax.
+ */
+pub fn build_from_map(world: &mut World, map: &Map, parent: Entity) -> Vec<Entity> {
+    let mut layers: Vec<(u32, MapLayer)> = Vec::new();
+    layers.extend(
+        map.layers
+            .iter()
+            .map(|layer| (layer.layer_index, MapLayer::Tiles(layer))),
+    );
+    layers.extend(
+        map.object_groups
+            .iter()
+            .map(|object_group| (object_group.layer_index, MapLayer::Objects(object_group))),
+    );
+    layers.extend(
+        map.image_layers
+            .iter()
+            .map(|image_layer| (image_layer.layer_index, MapLayer::Image(image_layer))),
+    );
+    layers.extend(
+        map.groups
+            .iter()
+            .map(|group| (group.layer_index, MapLayer::Group(group))),
+    );
+    layers.sort_by_key(|(layer_index, _)| *layer_index);
+
+    layers
+        .iter()
+        .enumerate()
+        .map(|(i, (_, layer))| add_map_layer_to_world(world, parent, map, layer, i as f32 + 1.0))
         .collect()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn prefab_lights_take_whole_numbers() {
+        let registry = PrefabRegistry::from_json(
+            r#"{ "lamp": { "light_radius": 48, "light_intensity": 2, "light_flicker": 0.5 } }"#,
+        );
+        let mut properties = Properties::new();
+        properties.insert(
+            "type".to_string(),
+            PropertyValue::StringValue("lamp".to_string()),
+        );
+
+        let light = light_from_properties(&registry.get_properties(&properties)).unwrap();
+        assert_eq!(light.radius, 48.0);
+        assert_eq!(light.intensity, 2.0);
+        assert_eq!(light.flicker, 0.5);
+    }
+
+    #[test]
+    fn objects_without_a_light_radius_get_no_light() {
+        let registry = PrefabRegistry::from_json(r#"{ "lamp": { "light_intensity": 2 } }"#);
+        let mut properties = Properties::new();
+        properties.insert(
+            "type".to_string(),
+            PropertyValue::StringValue("lamp".to_string()),
+        );
+
+        assert!(light_from_properties(&registry.get_properties(&properties)).is_none());
+    }
+}
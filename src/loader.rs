@@ -1,5 +1,5 @@
 use std::env;
-use std::fs::{create_dir, File};
+use std::fs::{create_dir, read_dir, File};
 use std::io::prelude::Read;
 use std::io::BufReader;
 use std::io::Result;
@@ -32,6 +32,15 @@ where
     (view, width as u16, height as u16)
 }
 
+pub fn load_font(name: &str) -> Vec<u8> {
+    let path = get_exe_path().join(format!("resources/fonts/{}.ttf", name));
+    let mut bytes = Vec::new();
+    if let Err(err) = File::open(&path).and_then(|mut file| file.read_to_end(&mut bytes)) {
+        panic!("Could not load font at {:?}: {}", path, err);
+    }
+    bytes
+}
+
 pub fn create_sound(sound_file_path: &str) -> Decoder<BufReader<File>> {
     let audio_file = File::open(&Path::new(&get_exe_path().join(sound_file_path))).unwrap();
     SoundDecoder::new(BufReader::new(audio_file)).unwrap()
@@ -45,6 +54,27 @@ pub fn load_map(path: &str) -> Map {
     }
 }
 
+/**
+ * Names of the files in the resource directory with the extension, without the extension
+ */
+pub fn list_resources(dir: &str, extension: &str) -> Vec<String> {
+    let path = get_exe_path().join(dir);
+    let entries = read_dir(&path).unwrap_or_else(|_| panic!("Could not read {:?}", path));
+
+    let mut names: Vec<String> = entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().and_then(|ext| ext.to_str()) == Some(extension))
+        .filter_map(|path| {
+            path.file_stem()
+                .and_then(|stem| stem.to_str())
+                .map(|stem| stem.to_string())
+        })
+        .collect();
+    names.sort();
+    names
+}
+
 pub fn read_text_from_file(path: &str) -> Result<String> {
     let path = get_exe_path().join(path);
     read_text_from_path(path)
@@ -2,14 +2,17 @@
 extern crate gfx;
 
 use std::collections::HashMap;
+use std::env;
+use std::fs::File;
+use std::io::Write;
+use std::mem;
 use std::time;
 
-use cgmath::Vector3;
 use gfx::Device;
 use gfx_glyph::GlyphBrushBuilder;
 use gfx_window_sdl;
 use sdl2::{self, event::Event, keyboard::Keycode};
-use specs::World;
+use specs::{Join, World};
 
 mod assets;
 mod components;
@@ -23,13 +26,12 @@ mod systems;
 mod utils;
 mod world;
 
-use assets::spritesheet_map::SpritesheetMap;
+use assets::{font_map::FontMap, spritesheet_map::SpritesheetMap};
 use components::{
-    tiled::TiledMap, AnimationSheet, Camera, Color, DeltaTime, EntityLookup, Input, Node,
-    ScreenChange, Shape, Sprite, Text, Transform,
+    AmbientLight, Camera, DebugOverlay, DeltaTime, FrameStats, Input, PostProcessing, ScreenChange,
 };
 use screen::{play::Play, ScreenManager};
-use world::{render_from_node, setup_world};
+use world::{render_debug_overlay, render_from_lookup, setup_world};
 
 const SCREEN_WIDTH: u32 = 960;
 const SCREEN_HEIGHT: u32 = 480;
@@ -55,20 +57,33 @@ fn main() -> Result<(), String> {
     let mut world = World::new();
     setup_world(&mut world);
 
-    let mut map_tilesets = HashMap::new();
-    map_tilesets.insert(
-        "tiles.png".to_string(),
-        loader::gfx_load_texture("resources/maps/tiles.png", &mut factory).0,
-    );
-
-    let demomap = loader::load_map("resources/maps/demomap.tmx");
-
     let mut tiled_maps = HashMap::new();
-    tiled_maps.insert("demomap".to_string(), demomap);
+    for name in loader::list_resources("resources/maps", "tmx") {
+        let map = loader::load_map(&format!("resources/maps/{}.tmx", name));
+        tiled_maps.insert(name, map);
+    }
 
-    let mut glyph_brush =
-        GlyphBrushBuilder::using_font_bytes(include_bytes!("../resources/Arial.ttf") as &[u8])
-            .build(factory.clone());
+    // maps share tilesets, so each image is only loaded once
+    let mut map_tilesets = HashMap::new();
+    for map in tiled_maps.values() {
+        for source in entities::get_image_sources(map) {
+            if !map_tilesets.contains_key(&source) {
+                let path = format!("resources/maps/{}", source);
+                map_tilesets.insert(source, loader::gfx_load_texture(&path, &mut factory).0);
+            }
+        }
+    }
+
+    // first font is the default for text without one set
+    let font_names = ["Arial", screen::play::KEYWORD_FONT];
+    let mut glyph_brush = GlyphBrushBuilder::using_fonts_bytes(
+        font_names
+            .iter()
+            .map(|name| loader::load_font(name))
+            .collect::<Vec<Vec<u8>>>(),
+    )
+    .depth_test(gfx::preset::depth::LESS_EQUAL_TEST)
+    .build(factory.clone());
 
     let spritesheet_map = SpritesheetMap::new(&mut factory, &["assets"]);
 
@@ -81,11 +96,28 @@ fn main() -> Result<(), String> {
         color: main_color,
         depth: main_depth,
     };
-    let mut renderer = renderer::Renderer::new(&mut factory, target.clone());
+    let mut renderer = renderer::Renderer::new(&mut factory, target);
+    renderer.set_default_filter(renderer::TextureFilter::Nearest);
+    renderer.set_pixel_snap(true);
+    renderer.set_font_map(FontMap::new(&font_names));
+
+    // screen text is sized for the base resolution, the window doesn't resize so once is enough
+    let (drawable_w, drawable_h) = window.drawable_size();
+    let scale_from_base_res = (
+        drawable_w as f32 / SCREEN_WIDTH as f32,
+        drawable_h as f32 / SCREEN_HEIGHT as f32,
+    );
 
     let mut events = sdl_context.event_pump().unwrap();
     let mut running = true;
     let mut frame_start = time::Instant::now();
+    let mut debug_was_pressed = false;
+
+    // set FRAME_STATS_CSV to a file path to record the FrameStats of every frame
+    let mut stats_csv = env::var("FRAME_STATS_CSV")
+        .ok()
+        .map(|path| File::create(&path).unwrap_or_else(|_| panic!("Could not create {}", path)));
+    let mut stats_header_written = false;
 
     while running {
         let duration = time::Instant::now() - frame_start;
@@ -118,60 +150,109 @@ fn main() -> Result<(), String> {
             }
         }
 
+        {
+            let debug_pressed = world.read_resource::<Input>().is_pressed("debug");
+            if debug_pressed && !debug_was_pressed {
+                world.write_resource::<DebugOverlay>().toggle();
+            }
+            debug_was_pressed = debug_pressed;
+        }
+
         screen_manager.update(&mut world);
         world.maintain();
 
-        encoder.clear(&target.color, [0.1, 0.2, 0.3, 1.0]);
-        encoder.clear_depth(&target.depth, 1.0);
+        encoder.clear(&renderer.target.color, [0.1, 0.2, 0.3, 1.0]);
+        encoder.clear_depth(&renderer.target.depth, 1.0);
 
-        {
-            let sprite_storage = world.read_storage::<Sprite>();
-            let mut transform_storage = world.write_storage::<Transform>();
-            let animation_sheet_storage = world.read_storage::<AnimationSheet>();
-            let color_storage = world.read_storage::<Color>();
-            let text_storage = world.read_storage::<Text>();
-            let shape_storage = world.read_storage::<Shape>();
-            let mut node_storage = world.write_storage::<Node>();
-            let tiled_map_storage = world.read_storage::<TiledMap>();
-
-            let root_entity = {
-                let lookup = world.read_resource::<EntityLookup>();
-                lookup.entities.get("root").unwrap().clone()
-            };
-
-            let mut offset_position = Vector3::<f32>::new(0.0, 0.0, 0.0);
-
-            render_from_node(
+        let render_start = time::Instant::now();
+        render_from_lookup(
+            &mut renderer,
+            &mut encoder,
+            "root",
+            &world,
+            &mut factory,
+            &spritesheet_map,
+            &map_tilesets,
+            &mut glyph_brush,
+            &scale_from_base_res,
+        );
+        renderer.render_lights(
+            &mut encoder,
+            &mut factory,
+            &world.read_resource::<Camera>(),
+            &world.read_resource::<AmbientLight>(),
+        );
+
+        // post processing is for the world, the rest goes straight to the window over it
+        renderer.present(
+            &mut encoder,
+            &mut factory,
+            &world.read_resource::<PostProcessing>(),
+        );
+        renderer.target_window(&mut encoder);
+
+        // the ui goes on top of the lights so it isn't darkened, and stays put on screen
+        let world_camera = mem::replace(
+            &mut *world.write_resource::<Camera>(),
+            Camera(renderer::get_ortho()),
+        );
+        render_from_lookup(
+            &mut renderer,
+            &mut encoder,
+            "ui",
+            &world,
+            &mut factory,
+            &spritesheet_map,
+            &map_tilesets,
+            &mut glyph_brush,
+            &scale_from_base_res,
+        );
+        *world.write_resource::<Camera>() = world_camera;
+        let render_time = utils::get_seconds(&render_start.elapsed());
+
+        // after the lights, so the overlay stays readable in dark scenes
+        if world.read_resource::<DebugOverlay>().enabled {
+            render_debug_overlay(
                 &mut renderer,
                 &mut encoder,
-                root_entity,
                 &world,
                 &mut factory,
                 &spritesheet_map,
-                &map_tilesets,
                 &mut glyph_brush,
-                &sprite_storage,
-                &mut transform_storage,
-                &animation_sheet_storage,
-                &color_storage,
-                &text_storage,
-                &shape_storage,
-                &tiled_map_storage,
-                &mut node_storage,
-                &mut offset_position,
-                &(1.0, 1.0),
+                &scale_from_base_res,
+            );
+            let camera = world.read_resource::<Camera>();
+            renderer.flush(
+                &mut encoder,
+                &mut factory,
+                &spritesheet_map,
+                &camera,
+                "",
+                true,
             );
+            // the player labels are world text
+            renderer.draw_world_text(&mut encoder, &mut glyph_brush, &camera);
+        }
+        renderer.draw_screen_text(&mut encoder, &mut glyph_brush);
+        renderer.target_scene();
+
+        {
+            let entity_count = world.entities().join().count();
+            let mut frame_stats = world.write_resource::<FrameStats>();
+            frame_stats.frame_time = utils::get_seconds(&duration);
+            frame_stats.render_time = render_time;
+            frame_stats.render = renderer.take_stats();
+            frame_stats.entity_count = entity_count;
+
+            if let Some(file) = &mut stats_csv {
+                if !stats_header_written {
+                    writeln!(file, "{}", frame_stats.csv_header()).unwrap();
+                    stats_header_written = true;
+                }
+                writeln!(file, "{}", frame_stats.csv_row()).unwrap();
+            }
         }
 
-        // <- draw actual stuff here
-        renderer.flush(
-            &mut encoder,
-            &mut factory,
-            &spritesheet_map,
-            &world.read_resource::<Camera>(),
-            "",
-            true,
-        );
         encoder.flush(&mut device);
         window.gl_swap_window();
         device.cleanup();
@@ -0,0 +1,159 @@
+use cgmath::{Matrix4, SquareMatrix, Vector3};
+use gfx::{self, texture, traits::FactoryExt};
+
+use super::{add_quad_to_batch, create_fullscreen_quad, ColorFormat, Projection, Vertex};
+use crate::components::{Camera, Light};
+
+gfx_defines! {
+    pipeline light_pipe {
+        vbuf: gfx::VertexBuffer<Vertex> = (),
+        projection_cb: gfx::ConstantBuffer<Projection> = "b_Projection",
+        out: gfx::BlendTarget<ColorFormat> = ("Target0", gfx::state::ColorMask::all(), gfx::preset::blend::ADD),
+    }
+
+    pipeline composite_pipe {
+        vbuf: gfx::VertexBuffer<Vertex> = (),
+        tex: gfx::TextureSampler<[f32; 4]> = "t_Texture",
+        out: gfx::BlendTarget<ColorFormat> = ("Target0", gfx::state::ColorMask::all(), gfx::preset::blend::MULTIPLY),
+    }
+}
+
+/**
+ * Offscreen target the lights are added into, on top of the ambient colour. It then gets
+ * multiplied over the scene, so anything outside of a light's radius takes the ambient level.
+ */
+pub struct LightMap<R: gfx::Resources> {
+    light_pso: gfx::PipelineState<R, light_pipe::Meta>,
+    composite_pso: gfx::PipelineState<R, composite_pipe::Meta>,
+    texture: (
+        gfx::handle::ShaderResourceView<R, [f32; 4]>,
+        gfx::handle::Sampler<R>,
+    ),
+    target: gfx::handle::RenderTargetView<R, ColorFormat>,
+    fullscreen_quad: (gfx::handle::Buffer<R, Vertex>, gfx::Slice<R>),
+    lights: Vec<Vertex>,
+}
+
+impl<R> LightMap<R>
+where
+    R: gfx::Resources,
+{
+    pub fn new<F>(factory: &mut F, width: u16, height: u16) -> LightMap<R>
+    where
+        F: gfx::Factory<R>,
+    {
+        let light_pso = factory
+            .create_pipeline_simple(
+                include_bytes!("shaders/basic.glslv"),
+                include_bytes!("shaders/light.glslf"),
+                light_pipe::new(),
+            )
+            .unwrap();
+
+        let composite_pso = factory
+            .create_pipeline_simple(
+                include_bytes!("shaders/fullscreen.glslv"),
+                include_bytes!("shaders/composite.glslf"),
+                composite_pipe::new(),
+            )
+            .unwrap();
+
+        let (_, view, target) = factory
+            .create_render_target::<ColorFormat>(width, height)
+            .unwrap();
+
+        let sampler = factory.create_sampler(texture::SamplerInfo::new(
+            texture::FilterMethod::Bilinear,
+            texture::WrapMode::Clamp,
+        ));
+
+        LightMap {
+            light_pso,
+            composite_pso,
+            texture: (view, sampler),
+            target,
+            fullscreen_quad: create_fullscreen_quad(factory),
+            lights: Vec::new(),
+        }
+    }
+
+    pub fn add_light(&mut self, center: &Vector3<f32>, light: &Light) {
+        add_quad_to_batch(
+            &mut self.lights,
+            [
+                light.color[0],
+                light.color[1],
+                light.color[2],
+                light.current_intensity,
+            ],
+            center.x - light.radius,
+            center.y - light.radius,
+            center.z,
+            light.radius * 2.0,
+            light.radius * 2.0,
+            0.0,
+            0.0,
+            1.0,
+            1.0,
+        );
+    }
+
+    pub fn has_lights(&self) -> bool {
+        self.lights.len() > 0
+    }
+
+    pub fn draw<C, F>(
+        &mut self,
+        encoder: &mut gfx::Encoder<R, C>,
+        factory: &mut F,
+        camera: &Camera,
+        ambient: [f32; 4],
+        out: &gfx::handle::RenderTargetView<R, ColorFormat>,
+    ) where
+        C: gfx::CommandBuffer<R>,
+        F: gfx::Factory<R>,
+    {
+        encoder.clear(&self.target, ambient);
+
+        if self.lights.len() > 0 {
+            let mut index_data: Vec<u32> = Vec::with_capacity(self.lights.len() / 4 * 6);
+            for i in 0..(self.lights.len() / 4) as u32 {
+                let offset = i * 4;
+                index_data.extend_from_slice(&[
+                    offset,
+                    offset + 1,
+                    offset + 2,
+                    offset + 2,
+                    offset + 3,
+                    offset,
+                ]);
+            }
+
+            let (vbuf, slice) =
+                factory.create_vertex_buffer_with_slice(&self.lights, &index_data[..]);
+            let params = light_pipe::Data {
+                vbuf,
+                projection_cb: factory.create_constant_buffer(1),
+                out: self.target.clone(),
+            };
+
+            encoder.update_constant_buffer(
+                &params.projection_cb,
+                &Projection {
+                    model: Matrix4::identity().into(),
+                    proj: camera.0.into(),
+                },
+            );
+            encoder.draw(&slice, &self.light_pso, &params);
+            self.lights.clear();
+        }
+
+        let (vbuf, slice) = self.fullscreen_quad.clone();
+        let params = composite_pipe::Data {
+            vbuf,
+            tex: self.texture.clone(),
+            out: out.clone(),
+        };
+        encoder.draw(&slice, &self.composite_pso, &params);
+    }
+}
@@ -1,18 +1,44 @@
+use std::collections::HashMap;
 use std::ops::Deref;
 
 use cgmath::{ortho, Matrix4, SquareMatrix, Vector3};
 use gfx::{self, texture, traits::FactoryExt};
-use gfx_glyph::{GlyphBrush, Layout, Section};
+use gfx_glyph::{
+    default_transform, BuiltInLineBreaker, FontId, GlyphBrush, Layout, OwnedVariedSection, Scale,
+    SectionText, VariedSection, VerticalAlign,
+};
 use specs::World;
 
 use crate::{
-    assets::spritesheet::Frame,
+    assets::font_map::FontMap,
     assets::spritesheet_map::SpritesheetMap,
-    components::{Camera, Color, Shape, Sprite, Text, Transform as ComponentTransform},
+    components::{
+        AmbientLight, Camera, Color, Light, Material, NineSlice, ParticleEmitter, PostProcessing,
+        RenderStats, Shape, Sprite, Text, TextSpace, TextWrap, Transform as ComponentTransform,
+        DEFAULT_EFFECT,
+    },
     loader::Texture,
     SCREEN_HEIGHT, SCREEN_WIDTH,
 };
 
+mod lighting;
+mod post_process;
+
+use self::lighting::LightMap;
+use self::post_process::PostProcessor;
+
+/**
+ * Material effects every renderer has. Like basic.glslf, each has to discard empty texels, or
+ * they write depth and cut holes in sprites drawn behind them
+ */
+const EFFECTS: [(&str, &[u8]); 5] = [
+    ("flash", include_bytes!("shaders/flash.glslf")),
+    ("dissolve", include_bytes!("shaders/dissolve.glslf")),
+    ("outline", include_bytes!("shaders/outline.glslf")),
+    ("palette_swap", include_bytes!("shaders/palette_swap.glslf")),
+    ("grayscale", include_bytes!("shaders/grayscale.glslf")),
+];
+
 pub type ColorFormat = gfx::format::Srgba8;
 pub type DepthFormat = gfx::format::Depth;
 
@@ -28,9 +54,16 @@ gfx_defines! {
         proj: [[f32; 4]; 4] = "u_Proj",
     }
 
+    constant MaterialParams {
+        color: [f32; 4] = "u_EffectColor",
+        params: [f32; 4] = "u_Params",
+        extra: [f32; 4] = "u_Extra",
+    }
+
     pipeline pipe {
         vbuf: gfx::VertexBuffer<Vertex> = (),
         projection_cb: gfx::ConstantBuffer<Projection> = "b_Projection",
+        material_cb: gfx::ConstantBuffer<MaterialParams> = "b_Material",
         tex: gfx::TextureSampler<[f32; 4]> = "t_Texture",
         out: gfx::BlendTarget<ColorFormat> = ("Target0", gfx::state::ColorMask::all(), gfx::preset::blend::ALPHA),
         depth: gfx::DepthTarget<DepthFormat> = gfx::preset::depth::LESS_EQUAL_WRITE,
@@ -51,23 +84,55 @@ pub fn get_ortho() -> Matrix4<f32> {
     m
 }
 
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TextureFilter {
+    /// Keeps pixel art crisp
+    Nearest,
+    Linear,
+}
+
 #[derive(Clone)]
 pub struct WindowTargets<R: gfx::Resources> {
     pub color: gfx::handle::RenderTargetView<R, ColorFormat>,
     pub depth: gfx::handle::DepthStencilView<R, DepthFormat>,
 }
 
+impl<'a> From<&'a Material> for MaterialParams {
+    fn from(material: &'a Material) -> Self {
+        MaterialParams {
+            color: material.color,
+            params: material.params,
+            extra: material.extra,
+        }
+    }
+}
+
 pub struct Renderer<R: gfx::Resources> {
-    pso: gfx::PipelineState<R, pipe::Meta>,
+    pipelines: HashMap<String, gfx::PipelineState<R, pipe::Meta>>,
+    material: Material,
+    default_material: Material,
     projection: Projection,
     model: Matrix4<f32>,
+    /// The offscreen scene target, see present for getting it to the window
     pub target: WindowTargets<R>,
+    window: WindowTargets<R>,
     color_texture: (
         gfx::handle::ShaderResourceView<R, [f32; 4]>,
         gfx::handle::Sampler<R>,
     ),
+    nearest_sampler: gfx::handle::Sampler<R>,
+    linear_sampler: gfx::handle::Sampler<R>,
+    default_filter: TextureFilter,
+    texture_filters: HashMap<String, TextureFilter>,
+    pixel_snap: bool,
     last_sheet: String,
     batch: Vec<Vertex>,
+    light_map: LightMap<R>,
+    post_processor: PostProcessor<R>,
+    font_map: FontMap,
+    stats: RenderStats,
+    /// Held back until the world text has been drawn, as it uses a different transform
+    screen_text: Vec<OwnedVariedSection>,
 }
 
 impl<R> Renderer<R>
@@ -88,6 +153,9 @@ where
             )
             .unwrap();
 
+        let mut pipelines = HashMap::new();
+        pipelines.insert(DEFAULT_EFFECT.to_string(), pso);
+
         let texels = [[0xff, 0xff, 0xff, 0xff]];
         let (_, texture_view) = factory
             .create_texture_immutable::<ColorFormat>(
@@ -97,41 +165,196 @@ where
             )
             .unwrap();
 
-        let sinfo =
-            texture::SamplerInfo::new(texture::FilterMethod::Mipmap, texture::WrapMode::Clamp);
+        let nearest_sampler = factory.create_sampler(texture::SamplerInfo::new(
+            texture::FilterMethod::Scale,
+            texture::WrapMode::Clamp,
+        ));
+        let linear_sampler = factory.create_sampler(texture::SamplerInfo::new(
+            texture::FilterMethod::Bilinear,
+            texture::WrapMode::Clamp,
+        ));
 
-        Renderer {
-            pso: pso,
+        let (width, height, _, _) = target.color.get_dimensions();
+        let light_map = LightMap::new(factory, width, height);
+        let post_processor = PostProcessor::new(factory, width, height);
+
+        let mut renderer = Renderer {
+            pipelines,
+            material: Material::default(),
+            default_material: Material::default(),
             projection: Projection {
                 model: Matrix4::identity().into(),
                 proj: get_ortho().into(),
             },
             model: Matrix4::identity(),
-            target,
-            color_texture: (texture_view, factory.create_sampler(sinfo)),
+            target: post_processor.get_scene_targets(),
+            window: target,
+            color_texture: (texture_view, nearest_sampler.clone()),
+            nearest_sampler,
+            linear_sampler,
+            default_filter: TextureFilter::Linear,
+            texture_filters: HashMap::new(),
+            pixel_snap: false,
             last_sheet: String::new(),
             batch: Vec::new(),
+            light_map,
+            post_processor,
+            font_map: FontMap::default(),
+            stats: RenderStats::default(),
+            screen_text: Vec::new(),
+        };
+
+        for (name, fragment_shader) in EFFECTS.iter() {
+            renderer.register_effect(factory, name, fragment_shader);
         }
+
+        renderer.register_post_pass(
+            factory,
+            "vignette",
+            include_bytes!("shaders/vignette.glslf"),
+            [0.6, 0.3, 0.0, 0.0],
+        );
+        renderer.register_post_pass(
+            factory,
+            "color_grade",
+            include_bytes!("shaders/color_grade.glslf"),
+            [1.0, 1.0, 1.0, 0.0],
+        );
+        renderer.register_post_pass(
+            factory,
+            "bloom",
+            include_bytes!("shaders/bloom.glslf"),
+            [0.7, 0.8, 2.0, 0.0],
+        );
+        renderer.register_post_pass(
+            factory,
+            "pixelate",
+            include_bytes!("shaders/pixelate.glslf"),
+            [4.0, 0.0, 0.0, 0.0],
+        );
+        renderer.register_post_pass(
+            factory,
+            "crt",
+            include_bytes!("shaders/crt.glslf"),
+            [0.25, 0.05, 0.0, 0.0],
+        );
+
+        renderer
     }
 
-    fn create_drawable_texture<F>(
-        &self,
+    /**
+     * Adds a fullscreen pass that the PostProcessing resource can then enable by name.
+     * Passes run in the order they're registered.
+     */
+    pub fn register_post_pass<F>(
+        &mut self,
         factory: &mut F,
+        name: &str,
+        fragment_shader: &[u8],
+        default_params: [f32; 4],
+    ) where
+        F: gfx::Factory<R>,
+    {
+        self.post_processor
+            .register_pass(factory, name, fragment_shader, default_params);
+    }
+
+    /**
+     * Runs the scene through the enabled post processing passes into the window
+     */
+    pub fn present<C, F>(
+        &mut self,
+        encoder: &mut gfx::Encoder<R, C>,
+        factory: &mut F,
+        post_processing: &PostProcessing,
+    ) where
+        C: gfx::CommandBuffer<R>,
+        F: gfx::Factory<R>,
+    {
+        self.post_processor
+            .present(encoder, factory, post_processing, &self.window);
+    }
+
+    /**
+     * Draws straight to the window until target_scene, for whatever goes over the scene after
+     * present, like the ui, so post processing leaves it alone
+     */
+    pub fn target_window<C>(&mut self, encoder: &mut gfx::Encoder<R, C>)
+    where
+        C: gfx::CommandBuffer<R>,
+    {
+        self.target = WindowTargets {
+            color: self.window.color.clone(),
+            depth: self.window.depth.clone(),
+        };
+        encoder.clear_depth(&self.target.depth, 1.0);
+    }
+
+    /// Back to drawing into the scene that present runs through post processing
+    pub fn target_scene(&mut self) {
+        self.target = self.post_processor.get_scene_targets();
+    }
+
+    /**
+     * Builds a pipeline from the basic vertex shader and the given fragment shader, which
+     * a Material can then refer to by name. Fragment shaders get the b_Material block.
+     */
+    pub fn register_effect<F>(&mut self, factory: &mut F, name: &str, fragment_shader: &[u8])
+    where
+        F: gfx::Factory<R>,
+    {
+        let pso = factory
+            .create_pipeline_simple(
+                include_bytes!("shaders/basic.glslv"),
+                fragment_shader,
+                pipe::new(),
+            )
+            .unwrap_or_else(|err| panic!("Could not build effect {}: {:?}", name, err));
+
+        self.pipelines.insert(name.to_string(), pso);
+    }
+
+    fn get_pipeline(&self, material: &Material) -> &gfx::PipelineState<R, pipe::Meta> {
+        self.pipelines
+            .get(&material.effect)
+            .unwrap_or_else(|| panic!("No effect registered with name {}", material.effect))
+    }
+
+    /// Filter used by any texture without one set through set_texture_filter
+    pub fn set_default_filter(&mut self, filter: TextureFilter) {
+        self.default_filter = filter;
+    }
+
+    /// Filter for a spritesheet or map tileset, by the name it's drawn with
+    pub fn set_texture_filter(&mut self, texture_name: &str, filter: TextureFilter) {
+        self.texture_filters
+            .insert(texture_name.to_string(), filter);
+    }
+
+    /// Rounds quad positions to whole pixels, so sprites don't shimmer when moving slowly
+    pub fn set_pixel_snap(&mut self, pixel_snap: bool) {
+        self.pixel_snap = pixel_snap;
+    }
+
+    fn create_drawable_texture(
+        &self,
+        texture_name: &str,
         texture: &Texture<R>,
     ) -> (
         gfx::handle::ShaderResourceView<R, [f32; 4]>,
         gfx::handle::Sampler<R>,
-    )
-    where
-        F: gfx::Factory<R>,
-    {
-        (
-            texture.clone(),
-            factory.create_sampler(texture::SamplerInfo::new(
-                texture::FilterMethod::Bilinear,
-                texture::WrapMode::Clamp,
-            )),
-        )
+    ) {
+        let filter = self
+            .texture_filters
+            .get(texture_name)
+            .unwrap_or(&self.default_filter);
+
+        let sampler = match filter {
+            TextureFilter::Nearest => self.nearest_sampler.clone(),
+            TextureFilter::Linear => self.linear_sampler.clone(),
+        };
+
+        (texture.clone(), sampler)
     }
 
     /**
@@ -146,6 +369,7 @@ where
         spritesheet_map: &SpritesheetMap<R>,
         texture_name: &String,
         texture: &Texture<R>,
+        offset_position: &Vector3<f32>,
     ) where
         R: gfx::Resources,
         C: gfx::CommandBuffer<R>,
@@ -153,14 +377,15 @@ where
     {
         let camera_res = world.read_resource::<Camera>();
         let camera = camera_res.deref();
-        // flush renderer batch if it has stuff
+        // layers are drawn right away, so sprites batched before them have to go first
+        let last_sheet = self.last_sheet.clone();
         self.flush(
             encoder,
             factory,
             spritesheet_map,
             &camera,
-            texture_name,
-            false,
+            &last_sheet,
+            true,
         );
 
         self.last_sheet = texture_name.to_owned();
@@ -182,10 +407,11 @@ where
         });
         let (vbuf, slice) = factory.create_vertex_buffer_with_slice(&batch, &index_data[..]);
 
-        let tex = self.create_drawable_texture(factory, texture);
+        let tex = self.create_drawable_texture(texture_name, texture);
         let params = pipe::Data {
             vbuf: vbuf,
             projection_cb: factory.create_constant_buffer(1),
+            material_cb: factory.create_constant_buffer(1),
             tex,
             out: self.target.color.clone(),
             depth: self.target.depth.clone(),
@@ -193,10 +419,21 @@ where
 
         self.projection.proj = (*camera).0.into();
 
-        self.projection.model = self.model.into();
+        // batch vertices are local to the entity, so move them by its position in the scene
+        let mut offset_position = *offset_position;
+        if self.pixel_snap {
+            offset_position.x = offset_position.x.round();
+            offset_position.y = offset_position.y.round();
+        }
+        self.projection.model = (self.model * Matrix4::from_translation(offset_position)).into();
 
         encoder.update_constant_buffer(&params.projection_cb, &self.projection);
-        encoder.draw(&slice, &self.pso, &params);
+        encoder.update_constant_buffer(
+            &params.material_cb,
+            &MaterialParams::from(&self.default_material),
+        );
+        encoder.draw(&slice, self.get_pipeline(&self.default_material), &params);
+        self.record_draw(batch.len());
     }
 
     fn draw_verticies<F, C>(
@@ -216,6 +453,13 @@ where
         // initialize as 1.5x, since we use 6 indices for 4 vertices
         let mut index_data: Vec<u32> = Vec::with_capacity((self.batch.len() as f32 * 1.5) as usize);
 
+        if self.pixel_snap {
+            for vertex in &mut self.batch {
+                vertex.pos[0] = vertex.pos[0].round();
+                vertex.pos[1] = vertex.pos[1].round();
+            }
+        }
+
         let mut offset = 0;
         for _ in self.batch.chunks(4) {
             index_data.push(0 + offset);
@@ -232,6 +476,7 @@ where
         let params = pipe::Data {
             vbuf: vbuf,
             projection_cb: factory.create_constant_buffer(1),
+            material_cb: factory.create_constant_buffer(1),
             tex: tex,
             out: self.target.color.clone(),
             depth: self.target.depth.clone(),
@@ -242,7 +487,10 @@ where
         self.projection.model = self.model.into();
 
         encoder.update_constant_buffer(&params.projection_cb, &self.projection);
-        encoder.draw(&slice, &self.pso, &params);
+        encoder.update_constant_buffer(&params.material_cb, &MaterialParams::from(&self.material));
+        encoder.draw(&slice, self.get_pipeline(&self.material), &params);
+        let vertices = self.batch.len();
+        self.record_draw(vertices);
     }
 
     pub fn flush<C, F>(
@@ -258,6 +506,10 @@ where
         F: gfx::Factory<R>,
     {
         if self.batch.len() > 0 && (self.last_sheet != current_sheet || force) {
+            if self.last_sheet != current_sheet {
+                self.stats.sheet_flushes += 1;
+            }
+
             let texture = if self.last_sheet == "white_texture" {
                 self.color_texture.clone()
             } else {
@@ -266,7 +518,7 @@ where
                     .get(&self.last_sheet)
                     .unwrap();
 
-                self.create_drawable_texture(factory, texture)
+                self.create_drawable_texture(&self.last_sheet, texture)
             };
 
             self.draw_verticies(encoder, factory, texture, &camera);
@@ -274,6 +526,44 @@ where
         }
     }
 
+    /**
+     * Draws what's batched for the current material if the given one differs
+     */
+    fn use_material<C, F>(
+        &mut self,
+        encoder: &mut gfx::Encoder<R, C>,
+        factory: &mut F,
+        spritesheet_map: &SpritesheetMap<R>,
+        camera: &Camera,
+        material: Option<&Material>,
+    ) where
+        C: gfx::CommandBuffer<R>,
+        F: gfx::Factory<R>,
+    {
+        let material = material.unwrap_or(&self.default_material).clone();
+        if material != self.material {
+            // the batch was built for the previous material, so draw it before switching
+            let last_sheet = self.last_sheet.clone();
+            self.flush(encoder, factory, spritesheet_map, camera, &last_sheet, true);
+            self.material = material;
+        }
+    }
+
+    fn use_sheet<C, F>(
+        &mut self,
+        encoder: &mut gfx::Encoder<R, C>,
+        factory: &mut F,
+        spritesheet_map: &SpritesheetMap<R>,
+        camera: &Camera,
+        sheet_name: &str,
+    ) where
+        C: gfx::CommandBuffer<R>,
+        F: gfx::Factory<R>,
+    {
+        self.flush(encoder, factory, spritesheet_map, camera, sheet_name, false);
+        self.last_sheet = sheet_name.to_string();
+    }
+
     pub fn render<C, F>(
         &mut self,
         encoder: &mut gfx::Encoder<R, C>,
@@ -283,6 +573,7 @@ where
         frame_name: Option<&String>,
         spritesheet_map: &SpritesheetMap<R>,
         color: Option<&Color>,
+        material: Option<&Material>,
         offset_position: &Vector3<f32>,
     ) where
         C: gfx::CommandBuffer<R>,
@@ -291,34 +582,20 @@ where
         let camera_res = world.read_resource::<Camera>();
         let camera = camera_res.deref();
 
+        self.use_material(encoder, factory, spritesheet_map, camera, material);
+
         let mut tx = 0.0;
         let mut ty = 0.0;
         let mut tx2 = 1.0;
         let mut ty2 = 1.0;
 
         let (w, h) = if let Some(frame_name) = frame_name {
-            let sheet_name = spritesheet_map.frame_to_sheet_name.get(frame_name).unwrap();
-            self.flush(
-                encoder,
-                factory,
-                spritesheet_map,
-                camera,
-                &sheet_name,
-                false,
-            );
-            self.last_sheet = sheet_name.clone();
-            let (spritesheet, _) = spritesheet_map.sheet_name_map.get(sheet_name).unwrap();
-            let region = spritesheet
-                .frames
-                .iter()
-                .filter(|frame| frame.filename == *frame_name)
-                .collect::<Vec<&Frame>>()[0];
-            let sw = spritesheet.meta.size.w as f32;
-            let sh = spritesheet.meta.size.h as f32;
-            tx = region.frame.x as f32 / sw;
-            ty = region.frame.y as f32 / sh;
-            tx2 = (region.frame.x as f32 + region.frame.w as f32) / sw;
-            ty2 = (region.frame.y as f32 + region.frame.h as f32) / sh;
+            let (sheet_name, uvs, size) = get_frame_region(spritesheet_map, frame_name);
+            self.use_sheet(encoder, factory, spritesheet_map, camera, sheet_name);
+            tx = uvs[0];
+            ty = uvs[1];
+            tx2 = uvs[2];
+            ty2 = uvs[3];
 
             if transform.flip {
                 let temp = tx2;
@@ -326,20 +603,9 @@ where
                 tx = temp;
             }
 
-            (
-                region.sprite_source_size.w as f32,
-                region.sprite_source_size.h as f32,
-            )
+            size
         } else {
-            self.flush(
-                encoder,
-                factory,
-                spritesheet_map,
-                camera,
-                "white_texture",
-                false,
-            );
-            self.last_sheet = "white_texture".to_string();
+            self.use_sheet(encoder, factory, spritesheet_map, camera, "white_texture");
             (transform.size.x as f32, transform.size.y as f32)
         };
 
@@ -364,12 +630,159 @@ where
         );
     }
 
+    /**
+     * Adds the nine quads of a nine slice to the sprite batch, filling the transform's size
+     */
+    pub fn render_nine_slice<C, F>(
+        &mut self,
+        encoder: &mut gfx::Encoder<R, C>,
+        world: &World,
+        factory: &mut F,
+        transform: &ComponentTransform,
+        nine_slice: &NineSlice,
+        spritesheet_map: &SpritesheetMap<R>,
+        color: Option<&Color>,
+        material: Option<&Material>,
+        offset_position: &Vector3<f32>,
+    ) where
+        C: gfx::CommandBuffer<R>,
+        F: gfx::Factory<R>,
+    {
+        let camera_res = world.read_resource::<Camera>();
+        let camera = camera_res.deref();
+
+        self.use_material(encoder, factory, spritesheet_map, camera, material);
+
+        let (sheet_name, uvs, (frame_w, frame_h)) =
+            get_frame_region(spritesheet_map, &nine_slice.frame_name);
+        self.use_sheet(encoder, factory, spritesheet_map, camera, sheet_name);
+
+        let color = if let Some(color) = color {
+            color.0
+        } else {
+            [1.0; 4]
+        };
+
+        let w = transform.size.x as f32;
+        let h = transform.size.y as f32;
+        // don't let the corners overlap when the transform is smaller than the insets
+        let left = nine_slice.left.min(w / 2.0);
+        let right = nine_slice.right.min(w / 2.0);
+        let top = nine_slice.top.min(h / 2.0);
+        let bottom = nine_slice.bottom.min(h / 2.0);
+
+        let xs = [0.0, left, w - right, w];
+        let ys = [0.0, top, h - bottom, h];
+
+        let u_per_pixel = (uvs[2] - uvs[0]) / frame_w;
+        let v_per_pixel = (uvs[3] - uvs[1]) / frame_h;
+        let us = [
+            uvs[0],
+            uvs[0] + nine_slice.left * u_per_pixel,
+            uvs[2] - nine_slice.right * u_per_pixel,
+            uvs[2],
+        ];
+        let vs = [
+            uvs[1],
+            uvs[1] + nine_slice.top * v_per_pixel,
+            uvs[3] - nine_slice.bottom * v_per_pixel,
+            uvs[3],
+        ];
+
+        for row in 0..3 {
+            for col in 0..3 {
+                let quad_w = xs[col + 1] - xs[col];
+                let quad_h = ys[row + 1] - ys[row];
+                if quad_w <= 0.0 || quad_h <= 0.0 {
+                    continue;
+                }
+
+                add_quad_to_batch(
+                    &mut self.batch,
+                    color,
+                    offset_position.x + xs[col],
+                    offset_position.y + ys[row],
+                    offset_position.z,
+                    quad_w,
+                    quad_h,
+                    us[col],
+                    vs[row],
+                    us[col + 1],
+                    vs[row + 1],
+                );
+            }
+        }
+    }
+
+    /**
+     * Adds every particle of the emitter to the sprite batch. Resolved frames are all on the
+     * same sheet, so an emitter never splits the batch.
+     */
+    pub fn render_particles<C, F>(
+        &mut self,
+        encoder: &mut gfx::Encoder<R, C>,
+        world: &World,
+        factory: &mut F,
+        particle_emitter: &mut ParticleEmitter,
+        spritesheet_map: &SpritesheetMap<R>,
+        material: Option<&Material>,
+        offset_position: &Vector3<f32>,
+    ) where
+        C: gfx::CommandBuffer<R>,
+        F: gfx::Factory<R>,
+    {
+        if particle_emitter.particles.len() == 0 {
+            return;
+        }
+
+        let camera_res = world.read_resource::<Camera>();
+        let camera = camera_res.deref();
+
+        self.use_material(encoder, factory, spritesheet_map, camera, material);
+
+        // only the first time they're drawn, or after the frames change
+        if particle_emitter.needs_frames_resolved() {
+            if let Err(err) = particle_emitter.resolve_frames(spritesheet_map) {
+                eprintln!("{}, drawing squares instead", err);
+            }
+        }
+
+        let sheet_name = particle_emitter.get_sheet().unwrap_or("white_texture");
+        self.use_sheet(encoder, factory, spritesheet_map, camera, sheet_name);
+
+        for particle in &particle_emitter.particles {
+            let (uvs, (w, h)) = match particle_emitter.get_region(particle) {
+                Some(region) => *region,
+                None => (
+                    [0.0, 0.0, 1.0, 1.0],
+                    (particle_emitter.size, particle_emitter.size),
+                ),
+            };
+
+            add_quad_to_batch(
+                &mut self.batch,
+                particle_emitter.get_color(particle),
+                offset_position.x + particle.pos.x - w / 2.0,
+                offset_position.y + particle.pos.y - h / 2.0,
+                offset_position.z,
+                w,
+                h,
+                uvs[0],
+                uvs[1],
+                uvs[2],
+                uvs[3],
+            );
+        }
+    }
+
     pub fn render_shape<C, F>(
         &mut self,
         encoder: &mut gfx::Encoder<R, C>,
         world: &World,
         factory: &mut F,
         shape: &Shape,
+        spritesheet_map: &SpritesheetMap<R>,
+        offset_position: &Vector3<f32>,
     ) where
         R: gfx::Resources,
         C: gfx::CommandBuffer<R>,
@@ -380,6 +793,10 @@ where
         let camera_res = world.read_resource::<Camera>();
         let camera = camera_res.deref();
 
+        // shapes are drawn right away, so anything batched before them has to go first
+        let last_sheet = self.last_sheet.clone();
+        self.flush(encoder, factory, spritesheet_map, camera, &last_sheet, true);
+
         let buffers = &shape.buffers;
         let (vbuf, slice) =
             factory.create_vertex_buffer_with_slice(&buffers.vertices[..], &buffers.indices[..]);
@@ -387,23 +804,141 @@ where
         let params = pipe::Data {
             vbuf: vbuf,
             projection_cb: factory.create_constant_buffer(1),
+            material_cb: factory.create_constant_buffer(1),
             tex: self.color_texture.clone(),
             out: self.target.color.clone(),
             depth: self.target.depth.clone(),
         };
 
+        let mut offset_position = *offset_position;
+        if self.pixel_snap {
+            offset_position.x = offset_position.x.round();
+            offset_position.y = offset_position.y.round();
+        }
+
         self.projection.proj = (*camera).0.into();
-        self.projection.model = self.model.into();
+        self.projection.model = (self.model * Matrix4::from_translation(offset_position)).into();
 
         encoder.update_constant_buffer(&params.projection_cb, &self.projection);
-        encoder.draw(&slice, &self.pso, &params);
+        encoder.update_constant_buffer(
+            &params.material_cb,
+            &MaterialParams::from(&self.default_material),
+        );
+        encoder.draw(&slice, self.get_pipeline(&self.default_material), &params);
+        self.record_draw(buffers.vertices.len());
+    }
+
+    pub fn render_light(
+        &mut self,
+        transform: &ComponentTransform,
+        light: &Light,
+        offset_position: &Vector3<f32>,
+    ) {
+        let center = Vector3::new(
+            offset_position.x + transform.size.x as f32 / 2.0,
+            offset_position.y + transform.size.y as f32 / 2.0,
+            offset_position.z,
+        );
+        self.light_map.add_light(&center, light);
+    }
+
+    /**
+     * Composites the lights queued this frame over everything drawn so far.
+     * Expects the sprite batch to be flushed already
+     */
+    pub fn render_lights<C, F>(
+        &mut self,
+        encoder: &mut gfx::Encoder<R, C>,
+        factory: &mut F,
+        camera: &Camera,
+        ambient_light: &AmbientLight,
+    ) where
+        C: gfx::CommandBuffer<R>,
+        F: gfx::Factory<R>,
+    {
+        // fully lit with nothing to add, no point in drawing the light map
+        if ambient_light.0 == [1.0; 4] && !self.light_map.has_lights() {
+            return;
+        }
+
+        self.light_map.draw(
+            encoder,
+            factory,
+            camera,
+            ambient_light.0,
+            &self.target.color,
+        );
     }
 
-    pub fn render_text<C, F>(
+    /**
+     * Adds a one pixel wide rectangle outline to the sprite batch, for the debug overlay
+     */
+    pub fn render_outline<C, F>(
         &mut self,
         encoder: &mut gfx::Encoder<R, C>,
+        factory: &mut F,
+        spritesheet_map: &SpritesheetMap<R>,
+        camera: &Camera,
+        position: &Vector3<f32>,
+        w: f32,
+        h: f32,
+        color: [f32; 4],
+    ) where
+        C: gfx::CommandBuffer<R>,
+        F: gfx::Factory<R>,
+    {
+        self.use_material(encoder, factory, spritesheet_map, camera, None);
+        self.use_sheet(encoder, factory, spritesheet_map, camera, "white_texture");
+
+        let edges = [
+            (position.x, position.y, w, 1.0),
+            (position.x, position.y + h - 1.0, w, 1.0),
+            (position.x, position.y, 1.0, h),
+            (position.x + w - 1.0, position.y, 1.0, h),
+        ];
+        for (x, y, w, h) in &edges {
+            add_quad_to_batch(
+                &mut self.batch,
+                color,
+                *x,
+                *y,
+                position.z,
+                *w,
+                *h,
+                0.0,
+                0.0,
+                1.0,
+                1.0,
+            );
+        }
+    }
+
+    /// Every draw creates a vertex buffer and the two constant buffers
+    fn record_draw(&mut self, vertices: usize) {
+        self.stats.draw_calls += 1;
+        self.stats.vertices += vertices as u32;
+        self.stats.buffers_created += 3;
+    }
+
+    /// What was drawn since the last time this was called
+    pub fn take_stats(&mut self) -> RenderStats {
+        let stats = self.stats;
+        self.stats = RenderStats::default();
+        stats
+    }
+
+    pub fn set_font_map(&mut self, font_map: FontMap) {
+        self.font_map = font_map;
+    }
+
+    /**
+     * Queues the text to be drawn in draw_world_text or draw_screen_text. World space text is
+     * placed in world units and takes the depth of its entity, screen space text is placed in
+     * window pixels over everything
+     */
+    pub fn render_text<F>(
+        &mut self,
         text: &Text,
-        transform: &ComponentTransform,
         color: &Color,
         glyph_brush: &mut GlyphBrush<R, F>,
         hidpi_factor: f32,
@@ -411,36 +946,162 @@ where
         offset_position: &Vector3<f32>,
     ) where
         R: gfx::Resources,
-        C: gfx::CommandBuffer<R>,
         F: gfx::Factory<R>,
     {
-        let mut scale = text.scale.clone();
-        scale.x *= hidpi_factor * scale_from_base_res.0;
-        scale.y *= hidpi_factor * scale_from_base_res.1;
-        let section = Section {
-            text: text.text.as_ref(),
-            scale,
-            bounds: (
+        // world text goes through the camera, so only screen text is scaled to the window
+        let (scale_x, scale_y) = match text.space {
+            TextSpace::World => (1.0, 1.0),
+            TextSpace::Screen => (
+                hidpi_factor * scale_from_base_res.0,
+                hidpi_factor * scale_from_base_res.1,
+            ),
+        };
+        let get_scale = |scale: Scale| Scale {
+            x: scale.x * scale_x,
+            y: scale.y * scale_y,
+        };
+        let font_id = if let Some(font) = &text.font {
+            self.font_map.get(font)
+        } else {
+            FontId(0)
+        };
+
+        let section_text = if text.spans.len() > 0 {
+            text.spans
+                .iter()
+                .map(|span| SectionText {
+                    text: span.text.as_ref(),
+                    scale: get_scale(span.scale.map(Scale::uniform).unwrap_or(text.scale)),
+                    color: span.color.unwrap_or(color.0),
+                    font_id: span
+                        .font
+                        .as_ref()
+                        .map(|font| self.font_map.get(font))
+                        .unwrap_or(font_id),
+                })
+                .collect()
+        } else {
+            vec![SectionText {
+                text: text.text.as_ref(),
+                scale: get_scale(text.scale),
+                color: color.0,
+                font_id,
+            }]
+        };
+
+        let layout = match text.wrap {
+            TextWrap::Word => Layout::default_wrap(),
+            TextWrap::AnyChar => {
+                Layout::default_wrap().line_breaker(BuiltInLineBreaker::AnyCharLineBreaker)
+            }
+            TextWrap::None => Layout::default_single_line(),
+        };
+
+        let bounds = match text.space {
+            TextSpace::World => (text.size.x as f32, text.size.y as f32),
+            TextSpace::Screen => (
                 text.size.x as f32 * hidpi_factor,
                 text.size.y as f32 * hidpi_factor,
             ),
+        };
+        // glyph brush aligns vertically around the position, so move it within the bounds
+        let v_offset = match text.v_align {
+            VerticalAlign::Top => 0.0,
+            VerticalAlign::Center => bounds.1 / 2.0,
+            VerticalAlign::Bottom => bounds.1,
+        };
+
+        let section = VariedSection {
+            text: section_text,
+            bounds,
             screen_position: (
-                offset_position.x * hidpi_factor * scale_from_base_res.0,
-                offset_position.y * hidpi_factor * scale_from_base_res.1,
+                offset_position.x * scale_x,
+                offset_position.y * scale_y + v_offset,
             ),
-            color: color.0,
-            z: 0.0,
-            layout: Layout::default().h_align(text.align),
-            ..Section::default()
+            z: match text.space {
+                TextSpace::World => offset_position.z,
+                // nearest depth for the glyph transform, in front of the whole scene
+                TextSpace::Screen => -1.0,
+            },
+            layout: layout.h_align(text.align).v_align(text.v_align),
+            ..VariedSection::default()
         };
 
-        glyph_brush.queue(section);
+        match text.space {
+            TextSpace::World => glyph_brush.queue(section),
+            TextSpace::Screen => self.screen_text.push(section.to_owned()),
+        }
+    }
+
+    /**
+     * Draws the world text queued since the last call through the camera, so it goes in with
+     * the scene it belongs to and gets lit along with it
+     */
+    pub fn draw_world_text<C, F>(
+        &mut self,
+        encoder: &mut gfx::Encoder<R, C>,
+        glyph_brush: &mut GlyphBrush<R, F>,
+        camera: &Camera,
+    ) where
+        C: gfx::CommandBuffer<R>,
+        F: gfx::Factory<R>,
+    {
+        glyph_brush
+            .draw_queued_with_transform(
+                camera.0.into(),
+                encoder,
+                &self.target.color,
+                &self.target.depth,
+            )
+            .unwrap();
+    }
+
+    /**
+     * Draws the screen text queued this frame, over everything else
+     */
+    pub fn draw_screen_text<C, F>(
+        &mut self,
+        encoder: &mut gfx::Encoder<R, C>,
+        glyph_brush: &mut GlyphBrush<R, F>,
+    ) where
+        C: gfx::CommandBuffer<R>,
+        F: gfx::Factory<R>,
+    {
+        for section in self.screen_text.drain(..) {
+            glyph_brush.queue(section.to_borrowed());
+        }
         glyph_brush
-            .draw_queued(encoder, &self.target.color, &self.target.depth)
+            .draw_queued_with_transform(
+                default_transform(&self.target.color),
+                encoder,
+                &self.target.color,
+                &self.target.depth,
+            )
             .unwrap();
     }
 }
 
+fn get_frame_region<'a, R: gfx::Resources>(
+    spritesheet_map: &'a SpritesheetMap<R>,
+    frame_name: &String,
+) -> (&'a str, [f32; 4], (f32, f32)) {
+    spritesheet_map
+        .get_frame_region(frame_name)
+        .unwrap_or_else(|| panic!("No spritesheet has the frame {}", frame_name))
+}
+
+fn create_fullscreen_quad<R, F>(factory: &mut F) -> (gfx::handle::Buffer<R, Vertex>, gfx::Slice<R>)
+where
+    R: gfx::Resources,
+    F: gfx::Factory<R>,
+{
+    let mut quad = Vec::with_capacity(4);
+    add_quad_to_batch(
+        &mut quad, [1.0; 4], -1.0, -1.0, 0.0, 2.0, 2.0, 0.0, 0.0, 1.0, 1.0,
+    );
+    factory.create_vertex_buffer_with_slice(&quad, &[0u16, 1, 2, 2, 3, 0][..])
+}
+
 fn add_quad_to_batch(
     batch: &mut Vec<Vertex>,
     color: [f32; 4],
@@ -475,3 +1136,22 @@ fn add_quad_to_batch(
         color: color,
     });
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn effects_discard_empty_texels() {
+        let basic: &[u8] = include_bytes!("shaders/basic.glslf");
+        for (name, fragment_shader) in EFFECTS.iter().chain(&[("basic", basic)]) {
+            let source = String::from_utf8_lossy(fragment_shader);
+            let source: String = source.split_whitespace().collect();
+            assert!(
+                source.contains("if(color.a<0.01){discard;}"),
+                "{} should discard texels with alpha below 0.01",
+                name
+            );
+        }
+    }
+}
@@ -0,0 +1,212 @@
+use gfx::{self, texture, traits::FactoryExt};
+
+use super::{create_fullscreen_quad, ColorFormat, DepthFormat, Vertex, WindowTargets};
+use crate::components::PostProcessing;
+
+gfx_defines! {
+    constant PostParams {
+        params: [f32; 4] = "u_Params",
+        resolution: [f32; 4] = "u_Resolution",
+    }
+
+    pipeline post_pipe {
+        vbuf: gfx::VertexBuffer<Vertex> = (),
+        post_cb: gfx::ConstantBuffer<PostParams> = "b_Post",
+        tex: gfx::TextureSampler<[f32; 4]> = "t_Texture",
+        out: gfx::RenderTarget<ColorFormat> = "Target0",
+    }
+}
+
+struct PostPass<R: gfx::Resources> {
+    name: String,
+    pso: gfx::PipelineState<R, post_pipe::Meta>,
+    default_params: [f32; 4],
+}
+
+struct OffscreenTarget<R: gfx::Resources> {
+    view: gfx::handle::ShaderResourceView<R, [f32; 4]>,
+    target: gfx::handle::RenderTargetView<R, ColorFormat>,
+}
+
+impl<R> OffscreenTarget<R>
+where
+    R: gfx::Resources,
+{
+    fn new<F>(factory: &mut F, width: u16, height: u16) -> OffscreenTarget<R>
+    where
+        F: gfx::Factory<R>,
+    {
+        let (_, view, target) = factory
+            .create_render_target::<ColorFormat>(width, height)
+            .unwrap();
+        OffscreenTarget { view, target }
+    }
+}
+
+/**
+ * Owns the offscreen target the scene is drawn into, and runs it through the enabled passes
+ * on the way to the window. Passes ping pong between two targets, the last one writes to the
+ * window.
+ */
+pub struct PostProcessor<R: gfx::Resources> {
+    passes: Vec<PostPass<R>>,
+    copy_pso: gfx::PipelineState<R, post_pipe::Meta>,
+    scene: OffscreenTarget<R>,
+    scene_depth: gfx::handle::DepthStencilView<R, DepthFormat>,
+    ping_pong: [OffscreenTarget<R>; 2],
+    sampler: gfx::handle::Sampler<R>,
+    fullscreen_quad: (gfx::handle::Buffer<R, Vertex>, gfx::Slice<R>),
+    resolution: [f32; 4],
+}
+
+impl<R> PostProcessor<R>
+where
+    R: gfx::Resources,
+{
+    pub fn new<F>(factory: &mut F, width: u16, height: u16) -> PostProcessor<R>
+    where
+        F: gfx::Factory<R>,
+    {
+        let copy_pso = factory
+            .create_pipeline_simple(
+                include_bytes!("shaders/fullscreen.glslv"),
+                include_bytes!("shaders/composite.glslf"),
+                post_pipe::new(),
+            )
+            .unwrap();
+
+        let scene_depth = factory
+            .create_depth_stencil_view_only::<DepthFormat>(width, height)
+            .unwrap();
+
+        let sampler = factory.create_sampler(texture::SamplerInfo::new(
+            texture::FilterMethod::Bilinear,
+            texture::WrapMode::Clamp,
+        ));
+
+        PostProcessor {
+            passes: Vec::new(),
+            copy_pso,
+            scene: OffscreenTarget::new(factory, width, height),
+            scene_depth,
+            ping_pong: [
+                OffscreenTarget::new(factory, width, height),
+                OffscreenTarget::new(factory, width, height),
+            ],
+            sampler,
+            fullscreen_quad: create_fullscreen_quad(factory),
+            resolution: [
+                width as f32,
+                height as f32,
+                1.0 / width as f32,
+                1.0 / height as f32,
+            ],
+        }
+    }
+
+    /// Where the scene should be drawn
+    pub fn get_scene_targets(&self) -> WindowTargets<R> {
+        WindowTargets {
+            color: self.scene.target.clone(),
+            depth: self.scene_depth.clone(),
+        }
+    }
+
+    pub fn register_pass<F>(
+        &mut self,
+        factory: &mut F,
+        name: &str,
+        fragment_shader: &[u8],
+        default_params: [f32; 4],
+    ) where
+        F: gfx::Factory<R>,
+    {
+        let pso = factory
+            .create_pipeline_simple(
+                include_bytes!("shaders/fullscreen.glslv"),
+                fragment_shader,
+                post_pipe::new(),
+            )
+            .unwrap_or_else(|err| panic!("Could not build post pass {}: {:?}", name, err));
+
+        self.passes.push(PostPass {
+            name: name.to_string(),
+            pso,
+            default_params,
+        });
+    }
+
+    fn draw_pass<C, F>(
+        &self,
+        encoder: &mut gfx::Encoder<R, C>,
+        factory: &mut F,
+        pso: &gfx::PipelineState<R, post_pipe::Meta>,
+        params: [f32; 4],
+        source: &gfx::handle::ShaderResourceView<R, [f32; 4]>,
+        out: &gfx::handle::RenderTargetView<R, ColorFormat>,
+    ) where
+        C: gfx::CommandBuffer<R>,
+        F: gfx::Factory<R>,
+    {
+        let (vbuf, slice) = self.fullscreen_quad.clone();
+        let data = post_pipe::Data {
+            vbuf,
+            post_cb: factory.create_constant_buffer(1),
+            tex: (source.clone(), self.sampler.clone()),
+            out: out.clone(),
+        };
+
+        encoder.update_constant_buffer(
+            &data.post_cb,
+            &PostParams {
+                params,
+                resolution: self.resolution,
+            },
+        );
+        encoder.draw(&slice, pso, &data);
+    }
+
+    pub fn present<C, F>(
+        &self,
+        encoder: &mut gfx::Encoder<R, C>,
+        factory: &mut F,
+        post_processing: &PostProcessing,
+        window: &WindowTargets<R>,
+    ) where
+        C: gfx::CommandBuffer<R>,
+        F: gfx::Factory<R>,
+    {
+        let enabled: Vec<&PostPass<R>> = self
+            .passes
+            .iter()
+            .filter(|pass| post_processing.is_enabled(&pass.name))
+            .collect();
+
+        if enabled.len() == 0 {
+            self.draw_pass(
+                encoder,
+                factory,
+                &self.copy_pso,
+                [0.0; 4],
+                &self.scene.view,
+                &window.color,
+            );
+            return;
+        }
+
+        let mut source = &self.scene.view;
+        for (i, pass) in enabled.iter().enumerate() {
+            let params = post_processing
+                .get_params(&pass.name)
+                .unwrap_or(pass.default_params);
+
+            if i == enabled.len() - 1 {
+                self.draw_pass(encoder, factory, &pass.pso, params, source, &window.color);
+            } else {
+                let out = &self.ping_pong[i % 2];
+                self.draw_pass(encoder, factory, &pass.pso, params, source, &out.target);
+                source = &out.view;
+            }
+        }
+    }
+}
@@ -13,7 +13,7 @@ pub trait Screen {
 
 pub struct ScreenManager {
     current_state: String,
-    states: HashMap<String, Box<Screen>>,
+    states: HashMap<String, Box<dyn Screen>>,
     pub restart_next_frame: bool,
 }
 
@@ -26,7 +26,7 @@ impl ScreenManager {
         }
     }
 
-    pub fn add_state(&mut self, name: String, screen: Box<Screen>) {
+    pub fn add_state(&mut self, name: String, screen: Box<dyn Screen>) {
         self.states.insert(name, screen);
     }
 
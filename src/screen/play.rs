@@ -1,36 +1,257 @@
 use std::collections::HashMap;
 
-use specs::{world::Builder, Dispatcher, DispatcherBuilder, World};
-use tiled::Map;
+use specs::{world::Builder, Dispatcher, DispatcherBuilder, Entity, Join, World};
+use tiled::{Map, PropertyValue};
 
 use crate::{
-    components::{tiled::TiledMap, EntityLookup, Node, Transform},
+    components::{
+        tiled::color_from_argb, AmbientLight, CameraBounds, Color, EntityLookup, Facing, Input,
+        MapTransition, Node, Player, PostProcessing, Text, TextSpan, Transform,
+    },
     entities,
-    systems::{AnimationSystem, PlayerSystem},
+    systems::{
+        AnimationControllerSystem, AnimationSystem, CameraSystem, FacingSystem, LightSystem,
+        ParallaxSystem, ParticleSystem, PlayerSystem, Timed, TweenSystem, WarpSystem,
+    },
     SCREEN_HEIGHT, SCREEN_WIDTH,
 };
 
 use super::Screen;
 
+const START_MAP: &str = "demomap";
+/// For words the player should pay attention to in hints and dialogue
+pub const KEYWORD_FONT: &str = "DejaVuSerif-Bold";
+const KEYWORD_COLOR: [f32; 4] = [1.0, 0.8, 0.3, 1.0];
+
 pub struct Play<'a> {
     dispatcher: Dispatcher<'a, 'a>,
     pub tiled_maps: HashMap<String, Map>,
+    current_map: String,
+    paused: bool,
+    pause_was_pressed: bool,
 }
 
 impl<'a> Play<'a> {
     pub fn new(tiled_maps: HashMap<String, Map>) -> Self {
         Play {
             dispatcher: DispatcherBuilder::new()
-                .with(AnimationSystem::new(), "animation", &[])
-                .with(PlayerSystem::new(), "player", &[])
+                .with(Timed::new("player", PlayerSystem::new()), "player", &[])
+                .with(
+                    Timed::new("facing", FacingSystem::new()),
+                    "facing",
+                    &["player"],
+                )
+                .with(
+                    Timed::new("animation_controller", AnimationControllerSystem::new()),
+                    "animation_controller",
+                    &["facing"],
+                )
+                .with(
+                    Timed::new("animation", AnimationSystem::new()),
+                    "animation",
+                    &["animation_controller"],
+                )
+                .with(Timed::new("light", LightSystem::new()), "light", &[])
+                .with(
+                    Timed::new("particle", ParticleSystem::new()),
+                    "particle",
+                    &[],
+                )
+                .with(Timed::new("tween", TweenSystem::new()), "tween", &[])
+                .with(Timed::new("warp", WarpSystem::new()), "warp", &["player"])
+                .with(
+                    Timed::new("camera", CameraSystem::new()),
+                    "camera",
+                    &["player"],
+                )
+                .with(
+                    Timed::new("parallax", ParallaxSystem::new()),
+                    "parallax",
+                    &["camera"],
+                )
                 .build(),
             tiled_maps,
+            current_map: START_MAP.to_string(),
+            paused: false,
+            pause_was_pressed: false,
         }
     }
 
     pub fn get_name() -> String {
         "play".to_string()
     }
+
+    /**
+     * Builds the map under a new "map" node of the root, replacing the ambient light
+     */
+    fn load_map(&mut self, world: &mut World, name: &str) {
+        let map = self
+            .tiled_maps
+            .get(name)
+            .unwrap_or_else(|| panic!("No map named {} in resources/maps", name));
+
+        {
+            let mut ambient_light = world.write_resource::<AmbientLight>();
+            *ambient_light = match map.properties.get("ambient_light") {
+                Some(PropertyValue::ColorValue(argb)) => AmbientLight(color_from_argb(*argb)),
+                _ => AmbientLight::default(),
+            };
+        }
+
+        {
+            let mut bounds = world.write_resource::<CameraBounds>();
+            bounds.w = (map.width * map.tile_width) as f32;
+            bounds.h = (map.height * map.tile_height) as f32;
+        }
+
+        let root = *world.read_resource::<EntityLookup>().get("root").unwrap();
+        let map_node = world
+            .create_entity()
+            .with(Transform::visible_identity())
+            .with(Node::with_parent(root))
+            .build();
+
+        let entities_from_map = entities::build_from_map(world, map, map_node);
+
+        {
+            let mut node_storage = world.write_storage::<Node>();
+            node_storage
+                .get_mut(map_node)
+                .unwrap()
+                .add_many(entities_from_map);
+            node_storage.get_mut(root).unwrap().add(map_node);
+        }
+
+        world
+            .write_resource::<EntityLookup>()
+            .insert("map", map_node);
+        self.current_map = name.to_string();
+    }
+
+    /**
+     * Deletes everything under the map node except the entity to keep, and its children
+     */
+    fn unload_map(world: &mut World, keep: Entity) {
+        let map_node = *world.read_resource::<EntityLookup>().get("map").unwrap();
+
+        let mut to_delete = Vec::new();
+        let mut to_visit = vec![map_node];
+        {
+            let node_storage = world.read_storage::<Node>();
+            while let Some(entity) = to_visit.pop() {
+                if entity == keep {
+                    continue;
+                }
+                to_delete.push(entity);
+                if let Some(node) = node_storage.get(entity) {
+                    to_visit.extend(node.entities.iter().cloned());
+                }
+            }
+        }
+
+        world.delete_entities(&to_delete).unwrap();
+
+        let mut lookup = world.write_resource::<EntityLookup>();
+        lookup.entities.retain(|_, entity| world.is_alive(*entity));
+    }
+
+    /**
+     * Takes the entity out of its parent node's children, so the node doesn't keep it after
+     * it's deleted or moved
+     */
+    fn detach(world: &World, entity: Entity) {
+        let mut node_storage = world.write_storage::<Node>();
+        let parent = node_storage.get(entity).and_then(|node| node.parent);
+        if let Some(parent_node) = parent.and_then(|parent| node_storage.get_mut(parent)) {
+            parent_node.entities.retain(|child| *child != entity);
+        }
+    }
+
+    /**
+     * Swaps the current map for another, moving the player over as it is so it keeps its state.
+     * The player is placed at the named object, in the same node. Any player object in the
+     * new map is only used when starting on that map, so it gets removed. A warp to a map or
+     * spawn that doesn't exist is logged and ignored.
+     */
+    fn change_map(&mut self, world: &mut World, name: &str, spawn: &str) {
+        let player = (&world.entities(), &world.read_storage::<Player>())
+            .join()
+            .map(|(entity, _)| entity)
+            .next();
+        let player = match player {
+            Some(player) => player,
+            None => {
+                eprintln!("No player to move to map {}, ignoring the warp", name);
+                return;
+            }
+        };
+
+        // checked up front, so a bad warp leaves the current map alone
+        match self.tiled_maps.get(name) {
+            Some(map) if entities::has_object(map, spawn) => {}
+            Some(_) => {
+                eprintln!(
+                    "No spawn object {} in map {}, ignoring the warp",
+                    spawn, name
+                );
+                return;
+            }
+            None => {
+                eprintln!("No map named {} in resources/maps, ignoring the warp", name);
+                return;
+            }
+        }
+
+        // detach the player, so the old map's nodes can go without it
+        Self::detach(world, player);
+
+        // warps within the same map just move the player
+        if name != self.current_map {
+            Self::unload_map(world, player);
+            self.load_map(world, name);
+        }
+
+        let map_players: Vec<Entity> = (&world.entities(), &world.read_storage::<Player>())
+            .join()
+            .map(|(entity, _)| entity)
+            .filter(|entity| *entity != player)
+            .collect();
+        for map_player in &map_players {
+            Self::detach(world, *map_player);
+        }
+        world.delete_entities(&map_players).unwrap();
+        world
+            .write_resource::<EntityLookup>()
+            .entities
+            .retain(|_, entity| !map_players.contains(entity));
+
+        // the map was checked for the spawn above
+        let spawn_entity = *world
+            .read_resource::<EntityLookup>()
+            .get(&format!("object_{}", spawn))
+            .unwrap();
+
+        let mut node_storage = world.write_storage::<Node>();
+        let mut transform_storage = world.write_storage::<Transform>();
+
+        let spawn_parent = node_storage
+            .get(spawn_entity)
+            .and_then(|node| node.parent)
+            .unwrap();
+        let spawn_pos = *transform_storage.get(spawn_entity).unwrap().get_pos();
+
+        transform_storage
+            .get_mut(player)
+            .unwrap()
+            .set_pos2(spawn_pos.x, spawn_pos.y);
+        node_storage.get_mut(player).unwrap().parent = Some(spawn_parent);
+        node_storage.get_mut(spawn_parent).unwrap().add(player);
+
+        // so the jump to the spawn point doesn't turn the player
+        if let Some(facing) = world.write_storage::<Facing>().get_mut(player) {
+            facing.last_pos = None;
+        }
+    }
 }
 
 impl<'a> Screen for Play<'a> {
@@ -47,14 +268,11 @@ impl<'a> Screen for Play<'a> {
             .with(Node::new())
             .build();
 
-        let mut children = Vec::new();
-
-        let demomap = self.tiled_maps.get(&"demomap".to_string()).unwrap();
+        world.write_resource::<EntityLookup>().insert("root", root);
 
-        let entity = world
+        // drawn after the lights and without the camera, see render_from_lookup
+        let ui = world
             .create_entity()
-            .with(TiledMap::new(demomap))
-            .with(Node::with_parent(root))
             .with(Transform::visible(
                 0.0,
                 0.0,
@@ -62,22 +280,70 @@ impl<'a> Screen for Play<'a> {
                 SCREEN_WIDTH as u16,
                 SCREEN_HEIGHT as u16,
             ))
+            .with(Node::new())
             .build();
-        children.push(entity);
 
-        let entities_from_map = entities::build_from_map(world, &demomap);
+        world.write_resource::<EntityLookup>().insert("ui", ui);
 
-        let mut node_storage = world.write_storage::<Node>();
-        let node = node_storage.get_mut(root).unwrap();
-        node.add_many(children);
-        node.add_many(entities_from_map);
+        let hint = world
+            .create_entity()
+            .with(Transform::visible(
+                16.0,
+                SCREEN_HEIGHT as f32 - 40.0,
+                0.0,
+                600,
+                32,
+            ))
+            .with(Node::with_parent(ui))
+            .with(Color([1.0; 4]))
+            .with(Text::new_with_spans(
+                18.0,
+                600,
+                32,
+                vec![
+                    TextSpan::new("Move with the "),
+                    TextSpan::new("arrow keys")
+                        .font(KEYWORD_FONT)
+                        .color(KEYWORD_COLOR),
+                    TextSpan::new(", walk east to find the "),
+                    TextSpan::new("house")
+                        .font(KEYWORD_FONT)
+                        .color(KEYWORD_COLOR),
+                ],
+            ))
+            .build();
+        world.write_storage::<Node>().get_mut(ui).unwrap().add(hint);
 
-        let mut lookup = world.write_resource::<EntityLookup>();
-        lookup.insert("root", root);
+        self.load_map(world, START_MAP);
     }
 
     fn update(&mut self, world: &mut World) {
-        self.dispatcher.dispatch(&mut world.res);
+        let pause_pressed = world.read_resource::<Input>().is_pressed("pause");
+        if pause_pressed && !self.pause_was_pressed {
+            self.paused = !self.paused;
+            let mut post_processing = world.write_resource::<PostProcessing>();
+            if self.paused {
+                post_processing.set_params("color_grade", [0.0, 0.9, 0.8, 0.0]);
+                post_processing.enable("color_grade");
+            } else {
+                post_processing.disable("color_grade");
+            }
+        }
+        self.pause_was_pressed = pause_pressed;
+
+        if !self.paused {
+            self.dispatcher.dispatch(&mut world.res);
+
+            let map_transition = {
+                let mut map_transition_storage = world.write_resource::<MapTransition>();
+                let copy = map_transition_storage.clone();
+                map_transition_storage.reset();
+                copy
+            };
+            if map_transition.is_pending() {
+                self.change_map(world, &map_transition.map, &map_transition.spawn);
+            }
+        }
     }
 
     fn handle_custom_change(&mut self, action: &String, world: &mut World) {}
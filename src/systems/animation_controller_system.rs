@@ -0,0 +1,96 @@
+use std::ops::Deref;
+
+use specs::{Join, Read, ReadStorage, System, WriteStorage};
+
+use crate::components::{
+    AnimationController, AnimationSheet, ControllerState, DeltaTime, Facing, Transform,
+};
+
+/**
+ * Finds the animation by name for the facing if the state is directional, and whether to flip
+ */
+fn resolve_animation(
+    name: &str,
+    state: &ControllerState,
+    facing: Option<&Facing>,
+    animation_sheet: &AnimationSheet,
+) -> Option<(String, bool)> {
+    match facing {
+        Some(facing) if state.directional => facing.get_animation(name, animation_sheet),
+        _ if animation_sheet.animations.contains_key(name) => Some((name.to_string(), state.flip)),
+        _ => None,
+    }
+}
+
+/**
+ * Runs the transitions of every AnimationController, and applies the state it ends up in
+ * to the entity's AnimationSheet and Transform
+ */
+pub struct AnimationControllerSystem;
+
+impl AnimationControllerSystem {
+    pub fn new() -> AnimationControllerSystem {
+        AnimationControllerSystem {}
+    }
+}
+
+impl<'a> System<'a> for AnimationControllerSystem {
+    type SystemData = (
+        WriteStorage<'a, AnimationController>,
+        WriteStorage<'a, AnimationSheet>,
+        Read<'a, DeltaTime>,
+        ReadStorage<'a, Facing>,
+        WriteStorage<'a, Transform>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (
+            mut animation_controller_storage,
+            mut animation_sheet_storage,
+            delta_time_storage,
+            facing_storage,
+            mut transform_storage,
+        ) = data;
+
+        let dt = delta_time_storage.deref().dt;
+
+        for (controller, animation_sheet, facing, transform) in (
+            &mut animation_controller_storage,
+            &mut animation_sheet_storage,
+            facing_storage.maybe(),
+            &mut transform_storage,
+        )
+            .join()
+        {
+            let entered = controller.update(dt, animation_sheet.finished);
+
+            // applied every frame rather than on state changes, as the facing can change too
+            let state = controller.get_state();
+            let (animation, flip, still) =
+                match resolve_animation(&state.animation, state, facing, animation_sheet) {
+                    Some((animation, flip)) => (animation, flip, false),
+                    None => state
+                        .fallback
+                        .as_ref()
+                        .and_then(|fallback| {
+                            resolve_animation(fallback, state, facing, animation_sheet)
+                        })
+                        .map(|(animation, flip)| (animation, flip, true))
+                        .unwrap_or_else(|| panic!("No animation for state {}", controller.state)),
+                };
+
+            // states can share an animation, like walking and standing, so keep its frame
+            let changed = animation_sheet.current_animation != animation;
+            if changed {
+                animation_sheet.set_current_animation(&animation);
+            }
+            if entered || changed {
+                if let Some(frame) = state.frame {
+                    animation_sheet.set_frame(frame);
+                }
+            }
+            animation_sheet.playing = state.playing && !still;
+            transform.flip = flip;
+        }
+    }
+}
@@ -1,8 +1,8 @@
 use std::ops::Deref;
 
-use specs::{Join, Read, System, WriteStorage};
+use specs::{Entities, Join, Read, System, Write, WriteStorage};
 
-use crate::components::{AnimationSheet, DeltaTime};
+use crate::components::{AnimationEvent, AnimationEvents, AnimationSheet, DeltaTime};
 
 pub struct AnimationSystem;
 
@@ -13,23 +13,27 @@ impl AnimationSystem {
 }
 
 impl<'a> System<'a> for AnimationSystem {
-    type SystemData = (WriteStorage<'a, AnimationSheet>, Read<'a, DeltaTime>);
+    type SystemData = (
+        Entities<'a>,
+        WriteStorage<'a, AnimationSheet>,
+        Read<'a, DeltaTime>,
+        Write<'a, AnimationEvents>,
+    );
 
     fn run(&mut self, data: Self::SystemData) {
-        let (mut animation_sheet_storage, delta_time_storage) = data;
+        let (entities, mut animation_sheet_storage, delta_time_storage, mut animation_events) =
+            data;
         let dt = delta_time_storage.deref().dt;
+        // events only last until the next run
+        animation_events.events.clear();
 
-        for animation_sheet in (&mut animation_sheet_storage).join() {
-            if !animation_sheet.playing {
-                continue;
-            }
-            animation_sheet.time_passed += dt;
-            if animation_sheet.time_passed >= animation_sheet.frame_length {
-                animation_sheet.current_index += 1;
-                animation_sheet.time_passed = 0.0;
-                if animation_sheet.current_index >= animation_sheet.get_current_animation().len() {
-                    animation_sheet.current_index = 0;
-                }
+        for (entity, animation_sheet) in (&entities, &mut animation_sheet_storage).join() {
+            for kind in animation_sheet.update(dt) {
+                animation_events.events.push(AnimationEvent {
+                    entity,
+                    animation: animation_sheet.current_animation.clone(),
+                    kind,
+                });
             }
         }
     }
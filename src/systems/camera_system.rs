@@ -0,0 +1,57 @@
+use specs::{Entities, Join, Read, ReadStorage, System, WriteExpect, WriteStorage};
+
+use crate::{
+    components::{Camera, CameraBounds, Node, Player, Transform},
+    scene_graph::get_absolute_pos,
+    SCREEN_HEIGHT, SCREEN_WIDTH,
+};
+
+/**
+ * Keeps the player centered on screen, without showing past the edges of the map
+ */
+pub struct CameraSystem;
+
+impl CameraSystem {
+    pub fn new() -> CameraSystem {
+        CameraSystem {}
+    }
+}
+
+fn clamp_to_bounds(position: f32, screen_size: f32, bounds_size: f32) -> f32 {
+    position.min(bounds_size - screen_size).max(0.0)
+}
+
+impl<'a> System<'a> for CameraSystem {
+    type SystemData = (
+        WriteExpect<'a, Camera>,
+        Read<'a, CameraBounds>,
+        Entities<'a>,
+        WriteStorage<'a, Node>,
+        ReadStorage<'a, Player>,
+        WriteStorage<'a, Transform>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (mut camera, bounds, entities, node_storage, player_storage, transform_storage) = data;
+
+        for (entity, _) in (&entities, &player_storage).join() {
+            let pos = get_absolute_pos(entity, &node_storage, &transform_storage);
+            let size = transform_storage.get(entity).unwrap().size;
+            let center_x = pos.x + size.x as f32 / 2.0;
+            let center_y = pos.y + size.y as f32 / 2.0;
+
+            camera.set_position(
+                clamp_to_bounds(
+                    center_x - SCREEN_WIDTH as f32 / 2.0,
+                    SCREEN_WIDTH as f32,
+                    bounds.w,
+                ),
+                clamp_to_bounds(
+                    center_y - SCREEN_HEIGHT as f32 / 2.0,
+                    SCREEN_HEIGHT as f32,
+                    bounds.h,
+                ),
+            );
+        }
+    }
+}
@@ -0,0 +1,36 @@
+use cgmath::Vector2;
+use specs::{Join, ReadStorage, System, WriteStorage};
+
+use crate::components::{Facing, Transform};
+
+/**
+ * Turns every Facing that follows movement towards where its entity moved since last frame
+ */
+pub struct FacingSystem;
+
+impl FacingSystem {
+    pub fn new() -> FacingSystem {
+        FacingSystem {}
+    }
+}
+
+impl<'a> System<'a> for FacingSystem {
+    type SystemData = (WriteStorage<'a, Facing>, ReadStorage<'a, Transform>);
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (mut facing_storage, transform_storage) = data;
+
+        for (facing, transform) in (&mut facing_storage, &transform_storage).join() {
+            let pos = transform.get_pos();
+            let pos = Vector2::new(pos.x, pos.y);
+
+            if let Some(last_pos) = facing.last_pos {
+                if facing.follow_movement {
+                    let movement = pos - last_pos;
+                    facing.face(movement.x, movement.y);
+                }
+            }
+            facing.last_pos = Some(pos);
+        }
+    }
+}
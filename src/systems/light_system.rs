@@ -0,0 +1,37 @@
+use std::ops::Deref;
+
+use specs::{Join, Read, System, WriteStorage};
+
+use crate::components::{DeltaTime, Light};
+
+pub struct LightSystem;
+
+impl LightSystem {
+    pub fn new() -> LightSystem {
+        LightSystem {}
+    }
+}
+
+impl<'a> System<'a> for LightSystem {
+    type SystemData = (WriteStorage<'a, Light>, Read<'a, DeltaTime>);
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (mut light_storage, delta_time_storage) = data;
+        let dt = delta_time_storage.deref().dt;
+
+        for light in (&mut light_storage).join() {
+            if light.flicker <= 0.0 {
+                light.current_intensity = light.intensity;
+                continue;
+            }
+
+            light.time_passed += dt * light.flicker_speed;
+            let t = light.time_passed;
+            // a few out of phase waves, so the flicker doesn't look like a pulse
+            let noise = ((t * 2.3).sin() + (t * 5.7).sin() * 0.5 + (t * 13.1).sin() * 0.25) / 1.75
+                * 0.5
+                + 0.5;
+            light.current_intensity = light.intensity * (1.0 - light.flicker * noise);
+        }
+    }
+}
@@ -1,5 +1,23 @@
+mod animation_controller_system;
 mod animation_system;
+mod camera_system;
+mod facing_system;
+mod light_system;
+mod parallax_system;
+mod particle_system;
 mod player_system;
+mod timed;
+mod tween_system;
+mod warp_system;
 
+pub use self::animation_controller_system::*;
 pub use self::animation_system::*;
+pub use self::camera_system::*;
+pub use self::facing_system::*;
+pub use self::light_system::*;
+pub use self::parallax_system::*;
+pub use self::particle_system::*;
 pub use self::player_system::*;
+pub use self::timed::*;
+pub use self::tween_system::*;
+pub use self::warp_system::*;
@@ -0,0 +1,32 @@
+use specs::{Join, ReadExpect, ReadStorage, System, WriteStorage};
+
+use crate::components::{Camera, Parallax, Transform};
+
+pub struct ParallaxSystem;
+
+impl ParallaxSystem {
+    pub fn new() -> ParallaxSystem {
+        ParallaxSystem {}
+    }
+}
+
+impl<'a> System<'a> for ParallaxSystem {
+    type SystemData = (
+        ReadExpect<'a, Camera>,
+        ReadStorage<'a, Parallax>,
+        WriteStorage<'a, Transform>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (camera, parallax_storage, mut transform_storage) = data;
+        let camera_position = camera.position();
+
+        for (parallax, transform) in (&parallax_storage, &mut transform_storage).join() {
+            // the scene already moves by the full camera position, so take back the rest
+            transform.set_pos2(
+                parallax.origin.x + camera_position.x * (1.0 - parallax.factor.x),
+                parallax.origin.y + camera_position.y * (1.0 - parallax.factor.y),
+            );
+        }
+    }
+}
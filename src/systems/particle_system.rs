@@ -0,0 +1,26 @@
+use std::ops::Deref;
+
+use specs::{Join, Read, System, WriteStorage};
+
+use crate::components::{DeltaTime, ParticleEmitter};
+
+pub struct ParticleSystem;
+
+impl ParticleSystem {
+    pub fn new() -> ParticleSystem {
+        ParticleSystem {}
+    }
+}
+
+impl<'a> System<'a> for ParticleSystem {
+    type SystemData = (WriteStorage<'a, ParticleEmitter>, Read<'a, DeltaTime>);
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (mut particle_emitter_storage, delta_time_storage) = data;
+        let dt = delta_time_storage.deref().dt;
+
+        for particle_emitter in (&mut particle_emitter_storage).join() {
+            particle_emitter.update(dt);
+        }
+    }
+}
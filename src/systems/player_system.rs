@@ -2,10 +2,7 @@ use std::ops::Deref;
 
 use specs::{Join, Read, ReadStorage, System, WriteStorage};
 
-use crate::{
-    components::{AnimationSheet, DeltaTime, Input, Player, Sprite, Transform},
-    SCREEN_HEIGHT, SCREEN_WIDTH,
-};
+use crate::components::{AnimationController, CameraBounds, DeltaTime, Input, Player, Transform};
 
 const VELOCITY: f32 = 50.0;
 
@@ -19,7 +16,8 @@ impl PlayerSystem {
 
 impl<'a> System<'a> for PlayerSystem {
     type SystemData = (
-        WriteStorage<'a, AnimationSheet>,
+        WriteStorage<'a, AnimationController>,
+        Read<'a, CameraBounds>,
         Read<'a, DeltaTime>,
         Read<'a, Input>,
         ReadStorage<'a, Player>,
@@ -28,7 +26,8 @@ impl<'a> System<'a> for PlayerSystem {
 
     fn run(&mut self, data: Self::SystemData) {
         let (
-            mut animation_sheet_storage,
+            mut animation_controller_storage,
+            bounds,
             delta_time_storage,
             input,
             player_storage,
@@ -37,8 +36,8 @@ impl<'a> System<'a> for PlayerSystem {
 
         let dt = delta_time_storage.deref().dt;
 
-        for (animation_sheet, _, transform) in (
-            &mut animation_sheet_storage,
+        for (controller, _, transform) in (
+            (&mut animation_controller_storage).maybe(),
             &player_storage,
             &mut transform_storage,
         )
@@ -46,53 +45,37 @@ impl<'a> System<'a> for PlayerSystem {
         {
             let mut moving = false;
             if input.is_pressed("left") {
-                if animation_sheet.current_animation != "right" {
-                    animation_sheet.set_current_animation("right");
-                }
-                if !transform.flip {
-                    transform.flip = true;
-                }
                 transform.translate_x(-VELOCITY * dt);
                 moving = true;
             }
             if input.is_pressed("right") {
-                if animation_sheet.current_animation != "right" {
-                    animation_sheet.set_current_animation("right");
-                }
-                if transform.flip {
-                    transform.flip = false;
-                }
                 transform.translate_x(VELOCITY * dt);
                 moving = true;
             }
             if input.is_pressed("up") {
-                if animation_sheet.current_animation != "up" {
-                    animation_sheet.set_current_animation("up");
-                    transform.flip = false;
-                }
                 transform.translate_y(-VELOCITY * dt);
                 moving = true;
             }
             if input.is_pressed("down") {
-                if animation_sheet.current_animation != "down" {
-                    animation_sheet.set_current_animation("down");
-                    transform.flip = false;
-                }
                 transform.translate_y(VELOCITY * dt);
                 moving = true;
             }
 
-            animation_sheet.playing = moving;
+            // facing follows the movement, see FacingSystem
+            if let Some(controller) = controller {
+                controller.set_float("speed", if moving { VELOCITY } else { 0.0 });
+            }
+
             if moving {
                 let pos = transform.get_pos();
                 if pos.x < 0.0 {
                     transform.set_pos(0.0, pos.y, pos.z);
-                } else if pos.x > SCREEN_WIDTH as f32 - transform.size.x as f32 {
-                    transform.set_pos(SCREEN_WIDTH as f32 - transform.size.x as f32, pos.y, pos.z);
+                } else if pos.x > bounds.w - transform.size.x as f32 {
+                    transform.set_pos(bounds.w - transform.size.x as f32, pos.y, pos.z);
                 } else if pos.y < 0.0 {
                     transform.set_pos(pos.x, 0.0, pos.z);
-                } else if pos.y > SCREEN_HEIGHT as f32 - transform.size.y as f32 {
-                    transform.set_pos(pos.x, SCREEN_HEIGHT as f32 - transform.size.y as f32, pos.z);
+                } else if pos.y > bounds.h - transform.size.y as f32 {
+                    transform.set_pos(pos.x, bounds.h - transform.size.y as f32, pos.z);
                 }
             }
         }
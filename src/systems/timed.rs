@@ -0,0 +1,37 @@
+use std::time;
+
+use specs::{Read, System};
+
+use crate::{components::FrameStats, utils};
+
+/**
+ * Runs the wrapped system and records how long it took in FrameStats, under the given name
+ */
+pub struct Timed<S> {
+    name: String,
+    system: S,
+}
+
+impl<S> Timed<S> {
+    pub fn new(name: &str, system: S) -> Self {
+        Timed {
+            name: name.to_string(),
+            system,
+        }
+    }
+}
+
+impl<'a, S> System<'a> for Timed<S>
+where
+    S: System<'a>,
+{
+    type SystemData = (S::SystemData, Read<'a, FrameStats>);
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (system_data, frame_stats) = data;
+
+        let start = time::Instant::now();
+        self.system.run(system_data);
+        frame_stats.record_system(&self.name, utils::get_seconds(&start.elapsed()));
+    }
+}
@@ -0,0 +1,109 @@
+use std::ops::Deref;
+
+use cgmath::Vector2;
+use rusttype::Scale;
+use specs::{Entities, Join, Read, System, Write, WriteStorage};
+
+use crate::components::{
+    Color, DeltaTime, Text, Transform, Tween, TweenEvent, TweenEvents, TweenProperty,
+};
+
+fn lerp(from: f32, to: f32, t: f32) -> f32 {
+    from + (to - from) * t
+}
+
+fn lerp_vector(from: &Vector2<f32>, to: &Vector2<f32>, t: f32) -> Vector2<f32> {
+    Vector2::new(lerp(from.x, to.x, t), lerp(from.y, to.y, t))
+}
+
+pub struct TweenSystem;
+
+impl TweenSystem {
+    pub fn new() -> TweenSystem {
+        TweenSystem {}
+    }
+}
+
+impl<'a> System<'a> for TweenSystem {
+    type SystemData = (
+        Entities<'a>,
+        WriteStorage<'a, Color>,
+        Read<'a, DeltaTime>,
+        WriteStorage<'a, Text>,
+        WriteStorage<'a, Transform>,
+        WriteStorage<'a, Tween>,
+        Write<'a, TweenEvents>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (
+            entities,
+            mut color_storage,
+            delta_time_storage,
+            mut text_storage,
+            mut transform_storage,
+            mut tween_storage,
+            mut tween_events,
+        ) = data;
+
+        let dt = delta_time_storage.deref().dt;
+        // events only last until the next run
+        tween_events.completed.clear();
+
+        let mut finished = Vec::new();
+
+        for (entity, tween) in (&entities, &mut tween_storage).join() {
+            tween.update(dt);
+            if !tween.is_started() {
+                continue;
+            }
+
+            let t = tween.get_progress();
+            match &tween.property {
+                TweenProperty::Position(from, to) => {
+                    if let Some(transform) = transform_storage.get_mut(entity) {
+                        let pos = lerp_vector(from, to, t);
+                        transform.set_pos2(pos.x, pos.y);
+                    }
+                }
+                TweenProperty::Size(from, to) => {
+                    if let Some(transform) = transform_storage.get_mut(entity) {
+                        let size = lerp_vector(from, to, t);
+                        transform.size.x = size.x.max(0.0).round() as u16;
+                        transform.size.y = size.y.max(0.0).round() as u16;
+                    }
+                }
+                TweenProperty::Alpha(from, to) => {
+                    if let Some(color) = color_storage.get_mut(entity) {
+                        color.0[3] = lerp(*from, *to, t);
+                    }
+                }
+                TweenProperty::Rgb(from, to) => {
+                    if let Some(color) = color_storage.get_mut(entity) {
+                        for i in 0..3 {
+                            color.0[i] = lerp(from[i], to[i], t);
+                        }
+                    }
+                }
+                TweenProperty::TextScale(from, to) => {
+                    if let Some(text) = text_storage.get_mut(entity) {
+                        text.scale = Scale::uniform(lerp(*from, *to, t));
+                        text.new_data = true;
+                    }
+                }
+            }
+
+            if tween.is_finished() {
+                finished.push(entity);
+                tween_events.completed.push(TweenEvent {
+                    entity,
+                    name: tween.name.clone(),
+                });
+            }
+        }
+
+        for entity in finished {
+            tween_storage.remove(entity);
+        }
+    }
+}
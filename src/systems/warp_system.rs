@@ -0,0 +1,67 @@
+use cgmath::Vector2;
+use specs::{Entities, Join, ReadStorage, System, Write, WriteStorage};
+
+use crate::{
+    components::{MapTransition, Node, Player, Transform, Warp},
+    scene_graph::get_absolute_pos,
+};
+
+pub struct WarpSystem;
+
+impl WarpSystem {
+    pub fn new() -> WarpSystem {
+        WarpSystem {}
+    }
+}
+
+impl<'a> System<'a> for WarpSystem {
+    type SystemData = (
+        Entities<'a>,
+        Write<'a, MapTransition>,
+        WriteStorage<'a, Node>,
+        ReadStorage<'a, Player>,
+        WriteStorage<'a, Transform>,
+        WriteStorage<'a, Warp>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (
+            entities,
+            mut map_transition,
+            node_storage,
+            player_storage,
+            transform_storage,
+            mut warp_storage,
+        ) = data;
+
+        // [x, y, x2, y2] in world space
+        let get_bounds = |entity| {
+            let pos = get_absolute_pos(entity, &node_storage, &transform_storage);
+            let size = transform_storage
+                .get(entity)
+                .map(|transform| transform.size)
+                .unwrap_or_else(|| Vector2::new(0, 0));
+            [pos.x, pos.y, pos.x + size.x as f32, pos.y + size.y as f32]
+        };
+
+        let player_bounds: Vec<[f32; 4]> = (&entities, &player_storage)
+            .join()
+            .map(|(entity, _)| get_bounds(entity))
+            .collect();
+
+        for (entity, warp) in (&entities, &mut warp_storage).join() {
+            let bounds = get_bounds(entity);
+            let overlapping = player_bounds.iter().any(|player| {
+                player[0] < bounds[2]
+                    && player[2] > bounds[0]
+                    && player[1] < bounds[3]
+                    && player[3] > bounds[1]
+            });
+
+            if overlapping && warp.armed && !map_transition.is_pending() {
+                map_transition.set(warp.map.clone(), warp.spawn.clone());
+            }
+            warp.armed = !overlapping;
+        }
+    }
+}
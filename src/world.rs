@@ -1,5 +1,5 @@
 use cgmath::Vector3;
-use gfx_glyph::GlyphBrush;
+use gfx_glyph::{GlyphBrush, VerticalAlign};
 use sdl2::keyboard::Keycode;
 use serde_json::{self, Value};
 use specs::{Entity, ReadStorage, World, WriteStorage};
@@ -8,18 +8,31 @@ use std::collections::{HashMap, HashSet};
 use crate::loader::read_text_from_file;
 
 use crate::{
-    assets::spritesheet_map::SpritesheetMap,
+    assets::{
+        animation_library::AnimationLibrary, prefabs::PrefabRegistry,
+        spritesheet_map::SpritesheetMap,
+    },
     components::{
-        tiled::TiledMap, AnimationSheet, Camera, Color, DeltaTime, EntityLookup, Input, Node,
-        Player, ScreenChange, Shape, Sprite, Text, Transform,
+        tiled::TiledMap, AmbientLight, AnimationController, AnimationEvents, AnimationSheet,
+        Camera, CameraBounds, Collider, Color, DebugOverlay, DeltaTime, EntityLookup, Facing,
+        FrameStats, Input, Light, MapTransition, Material, NineSlice, Node, Parallax,
+        ParticleEmitter, Player, PostProcessing, ScreenChange, Shape, Sprite, Text, TextSpace,
+        Transform, Tween, TweenEvents, Warp,
     },
     loader::Texture,
     renderer::{get_ortho, Renderer},
 };
 
 pub fn setup_world(world: &mut World) {
+    world.add_resource(AmbientLight::default());
+    world.add_resource(AnimationEvents::default());
+    world.add_resource(AnimationLibrary::new());
+    world.add_resource(PrefabRegistry::new());
+    world.add_resource(DebugOverlay::new());
+    world.add_resource(FrameStats::new());
     world.add_resource(DeltaTime::default());
     world.add_resource(Camera(get_ortho()));
+    world.add_resource(CameraBounds::default());
     world.add_resource(EntityLookup::new());
 
     let keybindings: Value = serde_json::from_str(
@@ -53,17 +66,30 @@ pub fn setup_world(world: &mut World) {
 
     world.add_resource(Input::new(1.0, actions));
 
+    world.add_resource(PostProcessing::new());
+    world.add_resource(MapTransition::new());
     world.add_resource(ScreenChange::new());
+    world.add_resource(TweenEvents::default());
 
+    world.register::<AnimationController>();
     world.register::<AnimationSheet>();
+    world.register::<Collider>();
     world.register::<Color>();
+    world.register::<Facing>();
+    world.register::<Light>();
+    world.register::<Material>();
+    world.register::<NineSlice>();
     world.register::<Node>();
+    world.register::<Parallax>();
+    world.register::<ParticleEmitter>();
     world.register::<Player>();
     world.register::<Shape>();
     world.register::<Sprite>();
     world.register::<Text>();
     world.register::<TiledMap>();
     world.register::<Transform>();
+    world.register::<Tween>();
+    world.register::<Warp>();
 }
 
 fn render_entity<R: gfx::Resources, C: gfx::CommandBuffer<R>, F: gfx::Factory<R>>(
@@ -79,6 +105,10 @@ fn render_entity<R: gfx::Resources, C: gfx::CommandBuffer<R>, F: gfx::Factory<R>
     transform_storage: &mut WriteStorage<Transform>,
     animation_storage: &ReadStorage<AnimationSheet>,
     color_storage: &ReadStorage<Color>,
+    light_storage: &ReadStorage<Light>,
+    material_storage: &ReadStorage<Material>,
+    nine_slice_storage: &ReadStorage<NineSlice>,
+    particle_emitter_storage: &mut WriteStorage<ParticleEmitter>,
     text_storage: &ReadStorage<Text>,
     shape_storage: &ReadStorage<Shape>,
     tiled_map_storage: &ReadStorage<TiledMap>,
@@ -95,6 +125,7 @@ fn render_entity<R: gfx::Resources, C: gfx::CommandBuffer<R>, F: gfx::Factory<R>
                 Some(&sprite.frame_name),
                 spritesheet,
                 color_storage.get(*entity),
+                material_storage.get(*entity),
                 offset_position,
             );
         }
@@ -108,16 +139,15 @@ fn render_entity<R: gfx::Resources, C: gfx::CommandBuffer<R>, F: gfx::Factory<R>
                 Some(animation.get_current_frame()),
                 spritesheet,
                 color_storage.get(*entity),
+                material_storage.get(*entity),
                 offset_position,
             );
         }
 
         if let (Some(color), Some(text)) = (color_storage.get(*entity), text_storage.get(*entity)) {
-            if text.text != "" && text.visible {
+            if text.has_content() && text.visible {
                 renderer.render_text(
-                    encoder,
                     &text,
-                    transform,
                     color,
                     glyph_brush,
                     world.read_resource::<Input>().hidpi_factor,
@@ -127,21 +157,61 @@ fn render_entity<R: gfx::Resources, C: gfx::CommandBuffer<R>, F: gfx::Factory<R>
             }
         }
 
+        if let Some(nine_slice) = nine_slice_storage.get(*entity) {
+            renderer.render_nine_slice(
+                encoder,
+                world,
+                factory,
+                &transform,
+                nine_slice,
+                spritesheet,
+                color_storage.get(*entity),
+                material_storage.get(*entity),
+                offset_position,
+            );
+        }
+
+        if let Some(particle_emitter) = particle_emitter_storage.get_mut(*entity) {
+            renderer.render_particles(
+                encoder,
+                world,
+                factory,
+                particle_emitter,
+                spritesheet,
+                material_storage.get(*entity),
+                offset_position,
+            );
+        }
+
+        if let Some(light) = light_storage.get(*entity) {
+            renderer.render_light(&transform, light, offset_position);
+        }
+
         if let Some(shape) = shape_storage.get(*entity) {
-            renderer.render_shape(encoder, world, factory, &shape);
+            renderer.render_shape(
+                encoder,
+                world,
+                factory,
+                &shape,
+                spritesheet,
+                offset_position,
+            );
         }
 
         if let Some(tile_map) = tiled_map_storage.get(*entity) {
             if let Some(texture) = map_tilesets.get(&tile_map.tileset) {
-                renderer.draw_batch(
-                    &tile_map.data,
-                    encoder,
-                    world,
-                    factory,
-                    spritesheet,
-                    &tile_map.tileset,
-                    texture,
-                );
+                if tile_map.opacity > 0.0 && tile_map.data.len() > 0 {
+                    renderer.draw_batch(
+                        &tile_map.data,
+                        encoder,
+                        world,
+                        factory,
+                        spritesheet,
+                        &tile_map.tileset,
+                        texture,
+                        offset_position,
+                    );
+                }
             } else {
                 panic!("Could not find texture by name {}", tile_map.tileset);
             }
@@ -162,6 +232,10 @@ pub fn render_from_node<R: gfx::Resources, C: gfx::CommandBuffer<R>, F: gfx::Fac
     transform_storage: &mut WriteStorage<Transform>,
     animation_sheet_storage: &ReadStorage<AnimationSheet>,
     color_storage: &ReadStorage<Color>,
+    light_storage: &ReadStorage<Light>,
+    material_storage: &ReadStorage<Material>,
+    nine_slice_storage: &ReadStorage<NineSlice>,
+    particle_emitter_storage: &mut WriteStorage<ParticleEmitter>,
     text_storage: &ReadStorage<Text>,
     shape_storage: &ReadStorage<Shape>,
     tiled_map_storage: &ReadStorage<TiledMap>,
@@ -193,6 +267,10 @@ pub fn render_from_node<R: gfx::Resources, C: gfx::CommandBuffer<R>, F: gfx::Fac
         transform_storage,
         animation_sheet_storage,
         color_storage,
+        light_storage,
+        material_storage,
+        nine_slice_storage,
+        particle_emitter_storage,
         text_storage,
         shape_storage,
         tiled_map_storage,
@@ -220,6 +298,10 @@ pub fn render_from_node<R: gfx::Resources, C: gfx::CommandBuffer<R>, F: gfx::Fac
             transform_storage,
             animation_sheet_storage,
             color_storage,
+            light_storage,
+            material_storage,
+            nine_slice_storage,
+            particle_emitter_storage,
             text_storage,
             shape_storage,
             tiled_map_storage,
@@ -236,3 +318,271 @@ pub fn render_from_node<R: gfx::Resources, C: gfx::CommandBuffer<R>, F: gfx::Fac
         offset_position.z -= pos.z;
     }
 }
+
+// in front of everything in the scene, which sits between 0 and 100
+const DEBUG_DEPTH: f32 = 99.0;
+const DEBUG_TRANSFORM_COLOR: [f32; 4] = [0.0, 1.0, 0.0, 1.0];
+const DEBUG_NODE_COLOR: [f32; 4] = [1.0, 0.8, 0.0, 1.0];
+const DEBUG_COLLIDER_COLOR: [f32; 4] = [0.0, 0.8, 1.0, 1.0];
+const DEBUG_SENSOR_COLOR: [f32; 4] = [1.0, 0.0, 1.0, 1.0];
+const DEBUG_TEXT_COLOR: [f32; 4] = [1.0, 1.0, 1.0, 1.0];
+
+fn union_bounds(bounds: Option<[f32; 4]>, other: [f32; 4]) -> [f32; 4] {
+    match bounds {
+        Some(bounds) => [
+            bounds[0].min(other[0]),
+            bounds[1].min(other[1]),
+            bounds[2].max(other[2]),
+            bounds[3].max(other[3]),
+        ],
+        None => other,
+    }
+}
+
+/**
+ * Renders everything under the named EntityLookup entry, then flushes the batch and the world
+ * text, so the root can be drawn separately from the ui. Does nothing when there's no such entry.
+ */
+pub fn render_from_lookup<R: gfx::Resources, C: gfx::CommandBuffer<R>, F: gfx::Factory<R>>(
+    renderer: &mut Renderer<R>,
+    encoder: &mut gfx::Encoder<R, C>,
+    name: &str,
+    world: &World,
+    factory: &mut F,
+    spritesheet: &SpritesheetMap<R>,
+    map_tilesets: &HashMap<String, Texture<R>>,
+    glyph_brush: &mut GlyphBrush<R, F>,
+    scale_from_base_res: &(f32, f32),
+) {
+    let entity = match world.read_resource::<EntityLookup>().get(name) {
+        Some(entity) => *entity,
+        None => return,
+    };
+
+    render_from_node(
+        renderer,
+        encoder,
+        entity,
+        world,
+        factory,
+        spritesheet,
+        map_tilesets,
+        glyph_brush,
+        &world.read_storage::<Sprite>(),
+        &mut world.write_storage::<Transform>(),
+        &world.read_storage::<AnimationSheet>(),
+        &world.read_storage::<Color>(),
+        &world.read_storage::<Light>(),
+        &world.read_storage::<Material>(),
+        &world.read_storage::<NineSlice>(),
+        &mut world.write_storage::<ParticleEmitter>(),
+        &world.read_storage::<Text>(),
+        &world.read_storage::<Shape>(),
+        &world.read_storage::<TiledMap>(),
+        &mut world.write_storage::<Node>(),
+        &mut Vector3::new(0.0, 0.0, 0.0),
+        scale_from_base_res,
+    );
+
+    let camera = world.read_resource::<Camera>();
+    renderer.flush(encoder, factory, spritesheet, &camera, "", true);
+    renderer.draw_world_text(encoder, glyph_brush, &camera);
+}
+
+/**
+ * Outlines the entity's transform, and the bounds of everything under it if it has a node.
+ * Returns the bounds as [x, y, x2, y2], or None when nothing visible is there.
+ */
+fn render_debug_node<R: gfx::Resources, C: gfx::CommandBuffer<R>, F: gfx::Factory<R>>(
+    renderer: &mut Renderer<R>,
+    encoder: &mut gfx::Encoder<R, C>,
+    world: &World,
+    factory: &mut F,
+    spritesheet: &SpritesheetMap<R>,
+    glyph_brush: &mut GlyphBrush<R, F>,
+    camera: &Camera,
+    hidpi_factor: f32,
+    entity: Entity,
+    animation_storage: &ReadStorage<AnimationSheet>,
+    collider_storage: &ReadStorage<Collider>,
+    node_storage: &ReadStorage<Node>,
+    player_storage: &ReadStorage<Player>,
+    transform_storage: &ReadStorage<Transform>,
+    mut offset_position: Vector3<f32>,
+) -> Option<[f32; 4]> {
+    let mut bounds = None;
+
+    if let Some(transform) = transform_storage.get(entity) {
+        if !transform.visible {
+            return None;
+        }
+
+        offset_position += *transform.get_pos();
+        let w = transform.size.x as f32;
+        let h = transform.size.y as f32;
+        renderer.render_outline(
+            encoder,
+            factory,
+            spritesheet,
+            camera,
+            &Vector3::new(offset_position.x, offset_position.y, DEBUG_DEPTH),
+            w,
+            h,
+            DEBUG_TRANSFORM_COLOR,
+        );
+        bounds = Some([
+            offset_position.x,
+            offset_position.y,
+            offset_position.x + w,
+            offset_position.y + h,
+        ]);
+    }
+
+    if let Some(collider) = collider_storage.get(entity) {
+        let color = if collider.sensor {
+            DEBUG_SENSOR_COLOR
+        } else {
+            DEBUG_COLLIDER_COLOR
+        };
+        renderer.render_shape(
+            encoder,
+            world,
+            factory,
+            &collider.to_shape(color).stroke_width(1.0),
+            spritesheet,
+            &Vector3::new(
+                offset_position.x + collider.offset.x,
+                offset_position.y + collider.offset.y,
+                DEBUG_DEPTH,
+            ),
+        );
+    }
+
+    if player_storage.get(entity).is_some() {
+        let mut info = format!("{:.1}, {:.1}", offset_position.x, offset_position.y);
+        if let Some(animation) = animation_storage.get(entity) {
+            info.push_str(&format!(
+                "\n{} [{}]",
+                animation.current_animation, animation.current_index
+            ));
+        }
+
+        let text = Text::new_with_text(8.0, 120, 24, info)
+            .v_align(VerticalAlign::Bottom)
+            .space(TextSpace::World);
+        renderer.render_text(
+            &text,
+            &Color(DEBUG_TEXT_COLOR),
+            glyph_brush,
+            hidpi_factor,
+            &(1.0, 1.0),
+            &Vector3::new(offset_position.x, offset_position.y - 24.0, DEBUG_DEPTH),
+        );
+    }
+
+    if let Some(node) = node_storage.get(entity) {
+        let mut children_bounds = None;
+        for child in &node.entities {
+            if let Some(child_bounds) = render_debug_node(
+                renderer,
+                encoder,
+                world,
+                factory,
+                spritesheet,
+                glyph_brush,
+                camera,
+                hidpi_factor,
+                *child,
+                animation_storage,
+                collider_storage,
+                node_storage,
+                player_storage,
+                transform_storage,
+                offset_position,
+            ) {
+                children_bounds = Some(union_bounds(children_bounds, child_bounds));
+            }
+        }
+
+        if let Some(children_bounds) = children_bounds {
+            renderer.render_outline(
+                encoder,
+                factory,
+                spritesheet,
+                camera,
+                &Vector3::new(children_bounds[0], children_bounds[1], DEBUG_DEPTH),
+                children_bounds[2] - children_bounds[0],
+                children_bounds[3] - children_bounds[1],
+                DEBUG_NODE_COLOR,
+            );
+            bounds = Some(union_bounds(bounds, children_bounds));
+        }
+    }
+
+    bounds
+}
+
+/**
+ * Draws transform boxes in green, node bounds in yellow and colliders in blue, or magenta
+ * for sensors. Labels the player with its position and animation, and lists the FrameStats
+ * in the corner.
+ */
+pub fn render_debug_overlay<R: gfx::Resources, C: gfx::CommandBuffer<R>, F: gfx::Factory<R>>(
+    renderer: &mut Renderer<R>,
+    encoder: &mut gfx::Encoder<R, C>,
+    world: &World,
+    factory: &mut F,
+    spritesheet: &SpritesheetMap<R>,
+    glyph_brush: &mut GlyphBrush<R, F>,
+    scale_from_base_res: &(f32, f32),
+) {
+    let camera = world.read_resource::<Camera>();
+    let hidpi_factor = world.read_resource::<Input>().hidpi_factor;
+    let root_entity = match world.read_resource::<EntityLookup>().get("root") {
+        Some(entity) => *entity,
+        None => return,
+    };
+
+    render_debug_node(
+        renderer,
+        encoder,
+        world,
+        factory,
+        spritesheet,
+        glyph_brush,
+        &camera,
+        hidpi_factor,
+        root_entity,
+        &world.read_storage::<AnimationSheet>(),
+        &world.read_storage::<Collider>(),
+        &world.read_storage::<Node>(),
+        &world.read_storage::<Player>(),
+        &world.read_storage::<Transform>(),
+        Vector3::new(0.0, 0.0, 0.0),
+    );
+
+    let frame_stats = world.read_resource::<FrameStats>();
+    let mut stats = format!(
+        "frame: {:.2} ms\nrender: {:.2} ms\ndraw calls: {}\nvertices: {}\n\
+         sheet flushes: {}\nbuffers: {}\nentities: {}",
+        frame_stats.frame_time * 1000.0,
+        frame_stats.render_time * 1000.0,
+        frame_stats.render.draw_calls,
+        frame_stats.render.vertices,
+        frame_stats.render.sheet_flushes,
+        frame_stats.render.buffers_created,
+        frame_stats.entity_count,
+    );
+    for (name, seconds) in frame_stats.get_system_times() {
+        stats.push_str(&format!("\n{}: {:.2} ms", name, seconds * 1000.0));
+    }
+    let text = Text::new_with_text(16.0, 240, 320, stats);
+    renderer.render_text(
+        &text,
+        &Color(DEBUG_TEXT_COLOR),
+        glyph_brush,
+        hidpi_factor,
+        scale_from_base_res,
+        &Vector3::new(8.0, 8.0, 0.0),
+    );
+}